@@ -0,0 +1,28 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Error Catchers
+//!
+//! ```
+//! ```
+use crate::error::{MoziasApiErr, MoziasApiErrKind};
+use crate::guards::auth::MissingScope;
+use rocket::{catch, Request};
+
+#[catch(403)]
+crate fn forbidden(req: &Request<'_>) -> MoziasApiErr {
+    match req.local_cache(MissingScope::default).scope() {
+        Some(scope) => MoziasApiErrKind::InsufficientScope(scope).into(),
+        None => MoziasApiErrKind::Forbidden.into(),
+    }
+}
+
+#[catch(401)]
+crate fn unauthorized(_req: &Request<'_>) -> MoziasApiErr {
+    MoziasApiErrKind::Unauthorized.into()
+}
@@ -0,0 +1,25 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Configuration
+//!
+//! ```
+//! ```
+use std::env;
+use std::str::FromStr;
+
+/// Parse the given environment variable, falling back to `default` if it is unset or invalid.
+crate fn env_or<T>(name: &str, default: T) -> T
+where
+    T: FromStr,
+{
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,136 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! API Key Database Access
+//!
+//! Scopes are stored space delimited, the same way they appear in tokens.
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::model::api_key::ApiKey;
+use chrono::NaiveDateTime;
+use lazy_static::lazy_static;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref INSERT_API_KEY: &'static str = r#"
+INSERT INTO mozias_api_key
+  (id, user_id, name, key_hash, scope, expires, created_date)
+VALUES
+  (:id, :user_id, :name, :key_hash, :scope, :expires, :created_date)"#;
+    static ref API_KEYS_BY_USER_ID_QUERY: &'static str = r#"
+SELECT id, name, scope, expires, last_used, created_date
+FROM mozias_api_key
+WHERE user_id = :user_id
+ORDER BY created_date DESC"#;
+    static ref API_KEY_BY_HASH_QUERY: &'static str = r#"
+SELECT api_key.id, user.id, user.username, api_key.scope
+FROM mozias_api_key as api_key
+INNER JOIN mozias_user as user on user.id = api_key.user_id
+WHERE api_key.key_hash = :key_hash
+  AND (api_key.expires IS NULL OR api_key.expires > NOW())
+  AND user.disabled = 0"#;
+    static ref TOUCH_API_KEY: &'static str = r#"
+UPDATE mozias_api_key
+SET last_used = NOW()
+WHERE id = :id"#;
+    static ref DELETE_API_KEY: &'static str = r#"
+DELETE FROM mozias_api_key
+WHERE id = :id AND user_id = :user_id"#;
+}
+
+type ApiKeyRow = (
+    String,
+    String,
+    String,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+    NaiveDateTime,
+);
+
+crate fn insert_api_key(
+    pool: &Pool,
+    user_id: &str,
+    api_key: &ApiKey,
+    key_hash: &str,
+) -> MoziasApiResult<()> {
+    match pool.prepare(*INSERT_API_KEY) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "id" => api_key.id(),
+                "user_id" => user_id,
+                "name" => api_key.name(),
+                "key_hash" => key_hash,
+                "scope" => api_key.scopes().join(" "),
+                "expires" => api_key.expires(),
+                "created_date" => api_key.created_date(),
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn api_keys_by_user_id(pool: &Pool, user_id: &str) -> MoziasApiResult<Vec<ApiKey>> {
+    Ok(pool
+        .prep_exec(*API_KEYS_BY_USER_ID_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter::<ApiKeyRow>)
+        .map(|(id, name, scope, expires, last_used, created_date)| {
+            let scopes = scope.split_whitespace().map(str::to_string).collect();
+            ApiKey::new(id, name, scopes, expires, last_used, created_date)
+        })
+        .collect())
+}
+
+/// Find the key id, user id, username, and scopes of an unexpired key
+/// belonging to an enabled user.
+crate fn api_key_by_hash(
+    pool: &Pool,
+    key_hash: &str,
+) -> MoziasApiResult<Vec<(String, String, String, String)>> {
+    Ok(pool
+        .prep_exec(*API_KEY_BY_HASH_QUERY, params! {"key_hash" => key_hash})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+crate fn touch_api_key(pool: &Pool, id: &str) -> MoziasApiResult<()> {
+    match pool.prepare(*TOUCH_API_KEY) {
+        Ok(mut stmt) => {
+            let _ = stmt.execute(params! {"id" => id})?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Delete one of the given user's keys, returning `false` if no such key exists.
+crate fn delete_api_key(pool: &Pool, id: &str, user_id: &str) -> MoziasApiResult<bool> {
+    match pool.prepare(*DELETE_API_KEY) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"id" => id, "user_id" => user_id})?;
+            Ok(result.affected_rows() == 1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
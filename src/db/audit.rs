@@ -0,0 +1,56 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Audit Database Access
+//!
+//! ```
+//! ```
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use lazy_static::lazy_static;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref INSERT_IMPERSONATION_AUDIT: &'static str = r#"
+INSERT INTO mozias_impersonation_audit
+  (actor_id, user_id, request_id, method, uri, status, created_date)
+VALUES
+  (:actor_id, :user_id, :request_id, :method, :uri, :status, NOW())"#;
+}
+
+/// Record a request an administrator made while impersonating a user.
+crate fn insert_impersonation_audit(
+    pool: &Pool,
+    actor_id: &str,
+    user_id: &str,
+    request_id: Option<&str>,
+    method: &str,
+    uri: &str,
+    status: u16,
+) -> MoziasApiResult<()> {
+    match pool.prepare(*INSERT_IMPERSONATION_AUDIT) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "actor_id" => actor_id,
+                "user_id" => user_id,
+                "request_id" => request_id,
+                "method" => method,
+                "uri" => uri,
+                "status" => status,
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
@@ -17,17 +17,36 @@ use mysql::{params, Pool};
 
 lazy_static! {
     static ref USER_AUTH_QUERY: &'static str = r#"
-SELECT user.id, profile.id as profile_id, password, refresh_token
+SELECT user.id, password, disabled, tfa_enabled
 FROM mozias_user as user
 LEFT JOIN mozias_user_profile as profile on user.id = profile.user_id
 WHERE user.username = :username"#;
-    static ref INSERT_REFRESH_TOKEN: &'static str = r#"
+    static ref TFA_INFO_QUERY: &'static str = r#"
+SELECT user.username, disabled, tfa_secret, tfa_enabled
+FROM mozias_user as user
+INNER JOIN mozias_user_profile as profile on user.id = profile.user_id
+WHERE user.id = :user_id"#;
+    static ref USERNAME_QUERY: &'static str = r#"
+SELECT username, disabled
+FROM mozias_user
+WHERE id = :user_id"#;
+    static ref USER_DISABLED_QUERY: &'static str = r#"
+SELECT disabled
+FROM mozias_user
+WHERE id = :user_id"#;
+    // Steps only move forward, so a code can't be used twice or after a later one
+    static ref USE_TFA_STEP: &'static str = r#"
+UPDATE mozias_user_profile
+SET tfa_last_step = :step
+WHERE user_id = :user_id AND (tfa_last_step IS NULL OR tfa_last_step < :step)"#;
+    static ref UPDATE_TFA: &'static str = r#"
 UPDATE mozias_user_profile
-SET refresh_token = :refresh_token
-WHERE id = :profile_id"#;
+SET tfa_secret = :tfa_secret, tfa_enabled = :tfa_enabled
+WHERE user_id = :user_id"#;
 }
 
-type AuthQueryResult = (String, String, String, Option<String>);
+type AuthQueryResult = (String, String, bool, bool);
+type TfaQueryResult = (String, bool, Option<String>, bool);
 
 crate fn auth_info_by_username(
     pool: &Pool,
@@ -39,16 +58,62 @@ crate fn auth_info_by_username(
         .collect())
 }
 
-crate fn add_refresh_token_to_profile(
+crate fn tfa_info_by_user_id(
+    pool: &Pool,
+    user_id: &str,
+) -> MoziasApiResult<Vec<TfaQueryResult>> {
+    Ok(pool
+        .prep_exec(*TFA_INFO_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+crate fn username_by_user_id(
+    pool: &Pool,
+    user_id: &str,
+) -> MoziasApiResult<Vec<(String, bool)>> {
+    Ok(pool
+        .prep_exec(*USERNAME_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+/// Is the given user disabled?  Users that no longer exist are treated as disabled.
+crate fn is_user_disabled(pool: &Pool, user_id: &str) -> MoziasApiResult<bool> {
+    let disabled: Vec<bool> = pool
+        .prep_exec(*USER_DISABLED_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter)
+        .collect();
+    Ok(disabled.first().cloned().unwrap_or(true))
+}
+
+/// Mark the given TOTP time step used, returning `false` if it, or a later
+/// one, already has been.
+crate fn use_tfa_step(pool: &Pool, user_id: &str, step: i64) -> MoziasApiResult<bool> {
+    match pool.prepare(*USE_TFA_STEP) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"step" => step, "user_id" => user_id})?;
+            Ok(result.affected_rows() == 1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn update_tfa(
     pool: &Pool,
-    profile_id: &str,
-    refresh_token: &str,
+    user_id: &str,
+    tfa_secret: Option<&str>,
+    tfa_enabled: bool,
 ) -> MoziasApiResult<()> {
-    match pool.prepare(*INSERT_REFRESH_TOKEN) {
+    match pool.prepare(*UPDATE_TFA) {
         Ok(mut stmt) => {
             let result = stmt.execute(params! {
-                "refresh_token" => refresh_token,
-                "profile_id" => profile_id,
+                "tfa_secret" => tfa_secret,
+                "tfa_enabled" => tfa_enabled,
+                "user_id" => user_id,
             })?;
 
             if result.affected_rows() != 1 {
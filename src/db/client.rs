@@ -0,0 +1,80 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Client Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::MoziasApiResult;
+use lazy_static::lazy_static;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref CLIENT_SECRET_HASH_QUERY: &'static str = r#"
+SELECT secret_hash
+FROM mozias_client
+WHERE id = :client_id AND disabled = 0"#;
+    static ref SERVICE_CLIENT_QUERY: &'static str = r#"
+SELECT service
+FROM mozias_client
+WHERE id = :client_id AND disabled = 0"#;
+    static ref CLIENT_SCOPES_QUERY: &'static str = r#"
+SELECT scope
+FROM mozias_client_scope
+WHERE client_id = :client_id
+ORDER BY scope"#;
+    static ref CLIENT_REDIRECT_URIS_QUERY: &'static str = r#"
+SELECT redirect_uri.redirect_uri
+FROM mozias_client_redirect_uri as redirect_uri
+INNER JOIN mozias_client as client on client.id = redirect_uri.client_id
+WHERE client.id = :client_id AND client.disabled = 0"#;
+}
+
+/// Find the hashed secret of an enabled client.  Public clients have no secret.
+crate fn client_secret_hash_by_id(
+    pool: &Pool,
+    client_id: &str,
+) -> MoziasApiResult<Vec<Option<String>>> {
+    Ok(pool
+        .prep_exec(
+            *CLIENT_SECRET_HASH_QUERY,
+            params! {"client_id" => client_id},
+        )?
+        .filter_map(result_filter)
+        .collect())
+}
+
+/// May the given client use the client credentials grant?  Unknown and
+/// disabled clients may not.
+crate fn is_service_client(pool: &Pool, client_id: &str) -> MoziasApiResult<bool> {
+    let service: Vec<bool> = pool
+        .prep_exec(*SERVICE_CLIENT_QUERY, params! {"client_id" => client_id})?
+        .filter_map(result_filter)
+        .collect();
+    Ok(service.first().cloned().unwrap_or(false))
+}
+
+/// The scopes the given client may be granted.
+crate fn client_scopes(pool: &Pool, client_id: &str) -> MoziasApiResult<Vec<String>> {
+    Ok(pool
+        .prep_exec(*CLIENT_SCOPES_QUERY, params! {"client_id" => client_id})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+/// The redirect URIs registered for an enabled client.
+crate fn client_redirect_uris(pool: &Pool, client_id: &str) -> MoziasApiResult<Vec<String>> {
+    Ok(pool
+        .prep_exec(
+            *CLIENT_REDIRECT_URIS_QUERY,
+            params! {"client_id" => client_id},
+        )?
+        .filter_map(result_filter)
+        .collect())
+}
@@ -0,0 +1,79 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Token Denylist Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::MoziasApiResult;
+use lazy_static::lazy_static;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref DENIED_TOKENS_QUERY: &'static str = r#"
+SELECT jti, UNIX_TIMESTAMP(expires)
+FROM mozias_token_denylist
+WHERE expires > NOW()"#;
+    // Revoking the same token twice is not an error
+    static ref INSERT_DENIED_TOKEN: &'static str = r#"
+INSERT IGNORE INTO mozias_token_denylist
+  (jti, subject_id, expires, revoked_by, revoked_date)
+VALUES
+  (:jti, :subject_id, FROM_UNIXTIME(:expires), :revoked_by, NOW())"#;
+    static ref PRUNE_DENIED_TOKENS: &'static str = r#"
+DELETE FROM mozias_token_denylist
+WHERE expires <= NOW()"#;
+}
+
+/// Every denied token that has not yet expired, with its `exp`.
+crate fn denied_tokens(pool: &Pool) -> MoziasApiResult<Vec<(String, i64)>> {
+    Ok(pool
+        .prep_exec(*DENIED_TOKENS_QUERY, ())?
+        .filter_map(result_filter)
+        .collect())
+}
+
+crate fn insert_denied_token(
+    pool: &Pool,
+    jti: &str,
+    subject_id: &str,
+    expires: i64,
+    revoked_by: &str,
+) -> MoziasApiResult<()> {
+    match pool.prepare(*INSERT_DENIED_TOKEN) {
+        Ok(mut stmt) => {
+            let _ = stmt.execute(params! {
+                "jti" => jti,
+                "subject_id" => subject_id,
+                "expires" => expires,
+                "revoked_by" => revoked_by,
+            })?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Delete the entries for tokens that have expired on their own.
+crate fn prune_denied_tokens(pool: &Pool) -> MoziasApiResult<()> {
+    match pool.prepare(*PRUNE_DENIED_TOKENS) {
+        Ok(mut stmt) => {
+            // No affected rows just means there was nothing to prune
+            let _ = stmt.execute(())?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
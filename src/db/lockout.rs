@@ -0,0 +1,150 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Login Lockout Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use lazy_static::lazy_static;
+use mysql::prelude::GenericConnection;
+use mysql::{params, Pool};
+
+crate const USERNAME: &str = "username";
+crate const IP: &str = "ip";
+// Failed TOTP codes, keyed by user id
+crate const TFA: &str = "tfa";
+// Password reset requests, keyed by username and by ip
+crate const RESET: &str = "reset";
+crate const RESET_IP: &str = "reset_ip";
+
+lazy_static! {
+    static ref LOCKED_UNTIL_QUERY: &'static str = r#"
+SELECT COALESCE(MAX(locked_until), 0)
+FROM mozias_login_attempt
+WHERE (kind = 'username' AND identifier = :username) OR (kind = 'ip' AND identifier = :ip)"#;
+    static ref LOCKED_UNTIL_BY_KIND_QUERY: &'static str = r#"
+SELECT COALESCE(MAX(locked_until), 0)
+FROM mozias_login_attempt
+WHERE kind = :kind AND identifier = :identifier"#;
+    static ref RECORD_FAILURE: &'static str = r#"
+INSERT INTO mozias_login_attempt
+  (kind, identifier, failures, last_failure, locked_until)
+VALUES
+  (:kind, :identifier, 1, NOW(), 0)
+ON DUPLICATE KEY UPDATE
+  failures = IF(last_failure < NOW() - INTERVAL :window SECOND, 1, failures + 1),
+  last_failure = NOW()"#;
+    static ref FAILURES_QUERY: &'static str = r#"
+SELECT failures
+FROM mozias_login_attempt
+WHERE kind = :kind AND identifier = :identifier"#;
+    static ref UPDATE_LOCKED_UNTIL: &'static str = r#"
+UPDATE mozias_login_attempt
+SET locked_until = :locked_until
+WHERE kind = :kind AND identifier = :identifier"#;
+    static ref CLEAR_FAILURES: &'static str = r#"
+DELETE FROM mozias_login_attempt
+WHERE kind = :kind AND identifier = :identifier"#;
+}
+
+/// The latest unix timestamp either the username or ip is locked out until.
+crate fn locked_until(pool: &Pool, username: &str, ip: Option<&str>) -> MoziasApiResult<i64> {
+    let locked_until: Vec<i64> = pool
+        .prep_exec(
+            *LOCKED_UNTIL_QUERY,
+            params! {"username" => username, "ip" => ip.unwrap_or("")},
+        )?
+        .filter_map(result_filter)
+        .collect();
+    Ok(locked_until.first().cloned().unwrap_or(0))
+}
+
+/// The unix timestamp the given identifier is locked out until.
+crate fn locked_until_by_kind(
+    pool: &Pool,
+    kind: &str,
+    identifier: &str,
+) -> MoziasApiResult<i64> {
+    let locked_until: Vec<i64> = pool
+        .prep_exec(
+            *LOCKED_UNTIL_BY_KIND_QUERY,
+            params! {"kind" => kind, "identifier" => identifier},
+        )?
+        .filter_map(result_filter)
+        .collect();
+    Ok(locked_until.first().cloned().unwrap_or(0))
+}
+
+/// Record a failed attempt, returning the number of failures in the current
+/// window.  Run it in a transaction so the count read back is this attempt's.
+crate fn record_failure<T>(
+    conn: &mut T,
+    kind: &str,
+    identifier: &str,
+    window: i64,
+) -> MoziasApiResult<u64>
+where
+    T: GenericConnection,
+{
+    let _ = conn.prep_exec(
+        *RECORD_FAILURE,
+        params! {"kind" => kind, "identifier" => identifier, "window" => window},
+    )?;
+    let failures: Vec<u64> = conn
+        .prep_exec(
+            *FAILURES_QUERY,
+            params! {"kind" => kind, "identifier" => identifier},
+        )?
+        .filter_map(result_filter)
+        .collect();
+    Ok(failures
+        .first()
+        .cloned()
+        .ok_or_else(|| MoziasApiErrKind::InsertFailed)?)
+}
+
+crate fn lock<T>(
+    conn: &mut T,
+    kind: &str,
+    identifier: &str,
+    locked_until: i64,
+) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*UPDATE_LOCKED_UNTIL) {
+        Ok(mut stmt) => {
+            let _ = stmt.execute(params! {
+                "locked_until" => locked_until,
+                "kind" => kind,
+                "identifier" => identifier,
+            })?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Clear the failures for the given identifier, returning `false` if there were none.
+crate fn clear_failures(pool: &Pool, kind: &str, identifier: &str) -> MoziasApiResult<bool> {
+    match pool.prepare(*CLEAR_FAILURES) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"kind" => kind, "identifier" => identifier})?;
+            Ok(result.affected_rows() == 1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
@@ -16,8 +16,19 @@ use mysql::prelude::FromRow;
 use mysql::{from_row_opt, OptsBuilder, Pool, Row, Transaction};
 use std::env;
 
+crate mod api_key;
+crate mod audit;
 crate mod auth;
+crate mod client;
+crate mod denylist;
+crate mod lockout;
+crate mod oauth;
+crate mod refresh;
+crate mod reset;
+crate mod role;
+crate mod session;
 crate mod telemetry;
+crate mod user;
 
 lazy_static! {
     static ref POOL: MoziasApiResult<Pool> = {
@@ -47,6 +58,25 @@ crate fn start_txn<'a>() -> MoziasApiResult<Transaction<'a>> {
     }
 }
 
+/// Run the given closure in a transaction, committing on success and rolling back on failure.
+crate fn in_txn<T, F>(f: F) -> MoziasApiResult<T>
+where
+    F: FnOnce(&mut Transaction<'_>) -> MoziasApiResult<T>,
+{
+    let mut txn = start_txn()?;
+
+    match f(&mut txn) {
+        Ok(result) => {
+            txn.commit()?;
+            Ok(result)
+        }
+        Err(e) => {
+            txn.rollback()?;
+            Err(e)
+        }
+    }
+}
+
 crate fn result_filter<T>(result: Result<Row, mysql::Error>) -> Option<T>
 where
     T: FromRow,
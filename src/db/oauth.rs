@@ -0,0 +1,134 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! OAuth Authorization Code Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::model::oauth::AuthorizationCode;
+use lazy_static::lazy_static;
+use mysql::prelude::GenericConnection;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref INSERT_AUTHORIZATION_CODE: &'static str = r#"
+INSERT INTO mozias_authorization_code
+  (code_hash, client_id, user_id, redirect_uri, code_challenge, scope, nonce, auth_time, expires, used, created_date)
+VALUES
+  (:code_hash, :client_id, :user_id, :redirect_uri, :code_challenge, :scope, :nonce, FROM_UNIXTIME(:auth_time), FROM_UNIXTIME(:expires), 0, NOW())"#;
+    static ref AUTHORIZATION_CODE_QUERY: &'static str = r#"
+SELECT client_id, user_id, redirect_uri, code_challenge, scope, nonce,
+  UNIX_TIMESTAMP(auth_time), UNIX_TIMESTAMP(expires)
+FROM mozias_authorization_code
+WHERE code_hash = :code_hash AND used = 0 AND expires > NOW()"#;
+    static ref USE_AUTHORIZATION_CODE: &'static str = r#"
+UPDATE mozias_authorization_code
+SET used = 1
+WHERE code_hash = :code_hash AND used = 0"#;
+}
+
+type AuthorizationCodeRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    i64,
+    i64,
+);
+
+crate fn insert_authorization_code(
+    pool: &Pool,
+    code: &AuthorizationCode,
+) -> MoziasApiResult<()> {
+    match pool.prepare(*INSERT_AUTHORIZATION_CODE) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "code_hash" => code.code_hash(),
+                "client_id" => code.client_id(),
+                "user_id" => code.user_id(),
+                "redirect_uri" => code.redirect_uri(),
+                "code_challenge" => code.code_challenge(),
+                "scope" => code.scope(),
+                "nonce" => code.nonce(),
+                "auth_time" => code.auth_time(),
+                "expires" => code.expires(),
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Find an unused, unexpired authorization code.
+crate fn authorization_code_by_hash<T>(
+    conn: &mut T,
+    code_hash: &str,
+) -> MoziasApiResult<Vec<AuthorizationCode>>
+where
+    T: GenericConnection,
+{
+    Ok(conn
+        .prep_exec(
+            *AUTHORIZATION_CODE_QUERY,
+            params! {"code_hash" => code_hash},
+        )?
+        .filter_map(result_filter::<AuthorizationCodeRow>)
+        .map(
+            |(
+                client_id,
+                user_id,
+                redirect_uri,
+                code_challenge,
+                scope,
+                nonce,
+                auth_time,
+                expires,
+            )| {
+                let mut code = AuthorizationCode::default();
+                let _ = code.set_code_hash(code_hash.to_string());
+                let _ = code.set_client_id(client_id);
+                let _ = code.set_user_id(user_id);
+                let _ = code.set_redirect_uri(redirect_uri);
+                let _ = code.set_code_challenge(code_challenge);
+                let _ = code.set_scope(scope);
+                let _ = code.set_nonce(nonce);
+                let _ = code.set_auth_time(auth_time);
+                let _ = code.set_expires(expires);
+                code
+            },
+        )
+        .collect())
+}
+
+/// Mark an authorization code as used, returning `false` if it already was.
+crate fn use_authorization_code<T>(conn: &mut T, code_hash: &str) -> MoziasApiResult<bool>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*USE_AUTHORIZATION_CODE) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"code_hash" => code_hash})?;
+            Ok(result.affected_rows() == 1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Refresh Token Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use lazy_static::lazy_static;
+use mysql::prelude::GenericConnection;
+use mysql::{params, Pool};
+
+crate const ACTIVE: &str = "active";
+crate const ROTATED: &str = "rotated";
+
+lazy_static! {
+    static ref INSERT_ISSUED_REFRESH_TOKEN: &'static str = r#"
+INSERT INTO mozias_refresh_token
+  (jti, family_id, user_id, status, expires)
+VALUES
+  (:jti, :family_id, :user_id, 'active', FROM_UNIXTIME(:expires))"#;
+    static ref ISSUED_REFRESH_TOKEN_QUERY: &'static str = r#"
+SELECT family_id, user_id, status
+FROM mozias_refresh_token
+WHERE jti = :jti"#;
+    static ref ROTATE_REFRESH_TOKEN: &'static str = r#"
+UPDATE mozias_refresh_token
+SET status = 'rotated'
+WHERE jti = :jti AND status = 'active'"#;
+    static ref REVOKE_REFRESH_TOKEN_FAMILY: &'static str = r#"
+UPDATE mozias_refresh_token
+SET status = 'revoked'
+WHERE family_id = :family_id AND status = 'active'"#;
+    static ref REVOKE_USER_REFRESH_TOKENS: &'static str = r#"
+UPDATE mozias_refresh_token
+SET status = 'revoked'
+WHERE user_id = :user_id AND status = 'active'"#;
+    static ref INSERT_REFRESH_TOKEN_REUSE: &'static str = r#"
+INSERT INTO mozias_refresh_token_reuse
+  (jti, family_id, user_id)
+VALUES
+  (:jti, :family_id, :user_id)"#;
+}
+
+type IssuedQueryResult = (String, String, String);
+
+crate fn issued_refresh_token_by_jti(
+    pool: &Pool,
+    jti: &str,
+) -> MoziasApiResult<Vec<IssuedQueryResult>> {
+    Ok(pool
+        .prep_exec(*ISSUED_REFRESH_TOKEN_QUERY, params! {"jti" => jti})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+crate fn insert_issued_refresh_token<T>(
+    conn: &mut T,
+    jti: &str,
+    family_id: &str,
+    user_id: &str,
+    expires: i64,
+) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*INSERT_ISSUED_REFRESH_TOKEN) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "jti" => jti,
+                "family_id" => family_id,
+                "user_id" => user_id,
+                "expires" => expires,
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Mark the given token as rotated.  Fails if the token was not active, which
+/// means another request rotated it first.
+crate fn rotate_refresh_token<T>(conn: &mut T, jti: &str) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*ROTATE_REFRESH_TOKEN) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"jti" => jti})?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::Unauthorized.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn revoke_refresh_token_family<T>(conn: &mut T, family_id: &str) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*REVOKE_REFRESH_TOKEN_FAMILY) {
+        Ok(mut stmt) => {
+            let _ = stmt.execute(params! {"family_id" => family_id})?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn revoke_user_refresh_tokens<T>(conn: &mut T, user_id: &str) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*REVOKE_USER_REFRESH_TOKENS) {
+        Ok(mut stmt) => {
+            let _ = stmt.execute(params! {"user_id" => user_id})?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn insert_refresh_token_reuse<T>(
+    conn: &mut T,
+    jti: &str,
+    family_id: &str,
+    user_id: &str,
+) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*INSERT_REFRESH_TOKEN_REUSE) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "jti" => jti,
+                "family_id" => family_id,
+                "user_id" => user_id,
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
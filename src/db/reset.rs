@@ -0,0 +1,100 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Password Reset Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use lazy_static::lazy_static;
+use mysql::prelude::GenericConnection;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref INSERT_PASSWORD_RESET: &'static str = r#"
+INSERT INTO mozias_password_reset
+  (id, user_id, token_hash, expires, used, created_date)
+VALUES
+  (:id, :user_id, :token_hash, FROM_UNIXTIME(:expires), 0, NOW())"#;
+    static ref USER_ID_BY_TOKEN_HASH_QUERY: &'static str = r#"
+SELECT user_id
+FROM mozias_password_reset
+WHERE token_hash = :token_hash AND used = 0 AND expires > NOW()"#;
+    static ref USE_PASSWORD_RESETS: &'static str = r#"
+UPDATE mozias_password_reset
+SET used = 1
+WHERE user_id = :user_id AND used = 0"#;
+}
+
+crate fn insert_password_reset(
+    pool: &Pool,
+    id: &str,
+    user_id: &str,
+    token_hash: &str,
+    expires: i64,
+) -> MoziasApiResult<()> {
+    match pool.prepare(*INSERT_PASSWORD_RESET) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "id" => id,
+                "user_id" => user_id,
+                "token_hash" => token_hash,
+                "expires" => expires,
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Find the user an unused, unexpired reset token was issued to.
+crate fn user_id_by_token_hash<T>(
+    conn: &mut T,
+    token_hash: &str,
+) -> MoziasApiResult<Vec<String>>
+where
+    T: GenericConnection,
+{
+    Ok(conn
+        .prep_exec(
+            *USER_ID_BY_TOKEN_HASH_QUERY,
+            params! {"token_hash" => token_hash},
+        )?
+        .filter_map(result_filter)
+        .collect())
+}
+
+/// Mark every outstanding reset token for the given user as used.
+crate fn use_password_resets<T>(conn: &mut T, user_id: &str) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*USE_PASSWORD_RESETS) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"user_id" => user_id})?;
+
+            // Zero rows means another request used the token first
+            if result.affected_rows() == 0 {
+                return Err(MoziasApiErrKind::Unauthorized.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
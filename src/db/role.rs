@@ -0,0 +1,34 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Role Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::MoziasApiResult;
+use crate::model::role::Role;
+use lazy_static::lazy_static;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref ROLES_BY_USER_ID_QUERY: &'static str = r#"
+SELECT role.id, role.name
+FROM mozias_role as role
+INNER JOIN mozias_user_role as user_role on role.id = user_role.role_id
+WHERE user_role.user_id = :user_id
+ORDER BY role.name"#;
+}
+
+crate fn find_roles_by_user_id(pool: &Pool, user_id: &str) -> MoziasApiResult<Vec<Role>> {
+    Ok(pool
+        .prep_exec(*ROLES_BY_USER_ID_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter::<(String, String)>)
+        .map(|(id, name)| Role::new(id, name))
+        .collect())
+}
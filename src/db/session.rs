@@ -0,0 +1,196 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Session Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::model::oauth::Grant;
+use crate::model::session::Session;
+use chrono::NaiveDateTime;
+use lazy_static::lazy_static;
+use mysql::prelude::GenericConnection;
+use mysql::{params, Pool};
+
+lazy_static! {
+    static ref SESSION_BY_REFRESH_TOKEN_QUERY: &'static str = r#"
+SELECT session.id, user.id, user.username, session.client_id, session.scope
+FROM mozias_session as session
+INNER JOIN mozias_user as user on user.id = session.user_id
+WHERE session.refresh_token_hash = :refresh_token_hash"#;
+    static ref SESSION_EXISTS_QUERY: &'static str = r#"
+SELECT COUNT(*)
+FROM mozias_session
+WHERE id = :id AND user_id = :user_id"#;
+    static ref SESSIONS_BY_USER_ID_QUERY: &'static str = r#"
+SELECT id, user_agent, ip, client_id, created_date, last_used_date
+FROM mozias_session
+WHERE user_id = :user_id
+ORDER BY last_used_date DESC"#;
+    static ref INSERT_SESSION: &'static str = r#"
+INSERT INTO mozias_session
+  (id, user_id, user_agent, ip, client_id, scope, created_date, last_used_date)
+VALUES
+  (:id, :user_id, :user_agent, :ip, :client_id, :scope, NOW(), NOW())"#;
+    static ref UPDATE_SESSION_REFRESH_TOKEN: &'static str = r#"
+UPDATE mozias_session
+SET refresh_token_hash = :refresh_token_hash, ip = :ip, last_used_date = NOW()
+WHERE id = :id"#;
+    static ref DELETE_SESSION: &'static str = r#"
+DELETE FROM mozias_session
+WHERE id = :id AND user_id = :user_id"#;
+    static ref DELETE_USER_SESSIONS: &'static str = r#"
+DELETE FROM mozias_session
+WHERE user_id = :user_id"#;
+}
+
+type SessionRow = (
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    NaiveDateTime,
+    NaiveDateTime,
+);
+type RefreshSessionRow = (String, String, String, Option<String>, Option<String>);
+
+crate fn session_by_refresh_token_hash(
+    pool: &Pool,
+    refresh_token_hash: &str,
+) -> MoziasApiResult<Vec<RefreshSessionRow>> {
+    Ok(pool
+        .prep_exec(
+            *SESSION_BY_REFRESH_TOKEN_QUERY,
+            params! {"refresh_token_hash" => refresh_token_hash},
+        )?
+        .filter_map(result_filter)
+        .collect())
+}
+
+/// Does the given user still have the given session?
+crate fn session_exists(pool: &Pool, id: &str, user_id: &str) -> MoziasApiResult<bool> {
+    let count: Vec<u64> = pool
+        .prep_exec(
+            *SESSION_EXISTS_QUERY,
+            params! {"id" => id, "user_id" => user_id},
+        )?
+        .filter_map(result_filter)
+        .collect();
+    Ok(count.first().map_or(false, |count| *count > 0))
+}
+
+crate fn sessions_by_user_id(pool: &Pool, user_id: &str) -> MoziasApiResult<Vec<Session>> {
+    Ok(pool
+        .prep_exec(*SESSIONS_BY_USER_ID_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter::<SessionRow>)
+        .map(
+            |(id, user_agent, ip, client_id, created_date, last_used_date)| {
+                Session::new(id, user_agent, ip, client_id, created_date, last_used_date)
+            },
+        )
+        .collect())
+}
+
+crate fn insert_session<T>(
+    conn: &mut T,
+    id: &str,
+    user_id: &str,
+    user_agent: &str,
+    ip: Option<&str>,
+    grant: Option<&Grant>,
+) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*INSERT_SESSION) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "id" => id,
+                "user_id" => user_id,
+                "user_agent" => user_agent,
+                "ip" => ip,
+                "client_id" => grant.map(|grant| grant.client_id().as_str()),
+                "scope" => grant.map(|grant| grant.scope().as_str()),
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn update_session_refresh_token<T>(
+    conn: &mut T,
+    id: &str,
+    refresh_token_hash: &str,
+    ip: Option<&str>,
+) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*UPDATE_SESSION_REFRESH_TOKEN) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "refresh_token_hash" => refresh_token_hash,
+                "ip" => ip,
+                "id" => id,
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Delete one of the given user's sessions, returning `false` if no such session exists.
+crate fn delete_session<T>(conn: &mut T, id: &str, user_id: &str) -> MoziasApiResult<bool>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*DELETE_SESSION) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {"id" => id, "user_id" => user_id})?;
+            Ok(result.affected_rows() == 1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn delete_user_sessions<T>(conn: &mut T, user_id: &str) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*DELETE_USER_SESSIONS) {
+        Ok(mut stmt) => {
+            // No affected rows just means there was nothing to revoke
+            let _ = stmt.execute(params! {"user_id" => user_id})?;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
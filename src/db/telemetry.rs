@@ -20,9 +20,9 @@ use rocket::http::{Cookie, Header};
 lazy_static! {
     static ref INSERT_TELEMETRY: &'static str = r#"
 INSERT INTO mozias_telemetry
-  (UUID, METHOD, URI, REMOTE, REAL_IP, STATUS, CONTENT_TYPE, ELAPSED)
+  (UUID, METHOD, URI, REMOTE, REAL_IP, STATUS, CONTENT_TYPE, ELAPSED, USER_ID, ACTOR_ID)
 VALUES
-  (:uuid, :method, :uri, :remote, :real_ip, :status, :content_type, :elapsed)
+  (:uuid, :method, :uri, :remote, :real_ip, :status, :content_type, :elapsed, :user_id, :actor_id)
 "#;
     static ref INSERT_HEADERS: &'static str = r#"
 INSERT INTO mozias_telemetry_headers
@@ -55,6 +55,8 @@ where
                 "status" => telemetry.status(),
                 "content_type" => telemetry.content_type(),
                 "elapsed" => elapsed,
+                "user_id" => telemetry.user_id(),
+                "actor_id" => telemetry.actor_id(),
             })?;
 
             if result.affected_rows() != 1 {
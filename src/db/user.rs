@@ -0,0 +1,202 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! User Database Access
+//!
+//! ```
+//! ```
+use crate::db::result_filter;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::model::auth::{User, UserProfile};
+use lazy_static::lazy_static;
+use mysql::prelude::GenericConnection;
+use mysql::{params, Pool};
+
+// MySQL ER_DUP_ENTRY
+const DUPLICATE_ENTRY: u16 = 1062;
+
+lazy_static! {
+    static ref USERNAME_COUNT_QUERY: &'static str = r#"
+SELECT COUNT(*)
+FROM mozias_user
+WHERE username = :username"#;
+    static ref NAMES_BY_USER_ID_QUERY: &'static str = r#"
+SELECT username, name
+FROM mozias_user
+WHERE id = :user_id"#;
+    static ref PASSWORD_BY_USER_ID_QUERY: &'static str = r#"
+SELECT password
+FROM mozias_user
+WHERE id = :user_id"#;
+    static ref UPDATE_PASSWORD: &'static str = r#"
+UPDATE mozias_user
+SET password = :password, last_modified_by = :last_modified_by, last_modified_date = NOW()
+WHERE id = :user_id"#;
+    // Only replaces the hash it was computed from, so it can't undo a password change
+    static ref REHASH_PASSWORD: &'static str = r#"
+UPDATE mozias_user
+SET password = :password, last_modified_by = :last_modified_by, last_modified_date = NOW()
+WHERE id = :user_id AND password = :old_password"#;
+    static ref INSERT_USER: &'static str = r#"
+INSERT INTO mozias_user
+  (id, username, password, name, disabled, created_by, created_date, last_modified_by, last_modified_date)
+VALUES
+  (:id, :username, :password, :name, :disabled, :created_by, :created_date, :last_modified_by, :last_modified_date)"#;
+    static ref INSERT_USER_PROFILE: &'static str = r#"
+INSERT INTO mozias_user_profile
+  (id, user_id, created_by, created_date, last_modified_by, last_modified_date)
+VALUES
+  (:id, :user_id, :created_by, :created_date, :last_modified_by, :last_modified_date)"#;
+}
+
+crate fn username_exists<T>(conn: &mut T, username: &str) -> MoziasApiResult<bool>
+where
+    T: GenericConnection,
+{
+    let counts: Vec<u64> = conn
+        .prep_exec(*USERNAME_COUNT_QUERY, params! {"username" => username})?
+        .filter_map(result_filter)
+        .collect();
+    Ok(counts.first().map_or(false, |count| *count > 0))
+}
+
+/// The username and display name of the given user
+crate fn names_by_user_id(
+    pool: &Pool,
+    user_id: &str,
+) -> MoziasApiResult<Vec<(String, String)>> {
+    Ok(pool
+        .prep_exec(*NAMES_BY_USER_ID_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+crate fn insert_user<T>(conn: &mut T, user: &User) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*INSERT_USER) {
+        Ok(mut stmt) => {
+            let result = match stmt.execute(params! {
+                "id" => user.id(),
+                "username" => user.username(),
+                "password" => user.password(),
+                "name" => user.name(),
+                "disabled" => user.disabled(),
+                "created_by" => user.created_by(),
+                "created_date" => user.created_date(),
+                "last_modified_by" => user.last_modified_by(),
+                "last_modified_date" => user.last_modified_date(),
+            }) {
+                Ok(result) => result,
+                // Lost a race with another registration for the same username
+                Err(mysql::Error::MySqlError(ref e)) if e.code == DUPLICATE_ENTRY => {
+                    return Err(MoziasApiErrKind::Conflict.into());
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn insert_user_profile<T>(conn: &mut T, profile: &UserProfile) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*INSERT_USER_PROFILE) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "id" => profile.id(),
+                "user_id" => profile.user_id(),
+                "created_by" => profile.created_by(),
+                "created_date" => profile.created_date(),
+                "last_modified_by" => profile.last_modified_by(),
+                "last_modified_date" => profile.last_modified_date(),
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+crate fn password_by_user_id(pool: &Pool, user_id: &str) -> MoziasApiResult<Vec<String>> {
+    Ok(pool
+        .prep_exec(*PASSWORD_BY_USER_ID_QUERY, params! {"user_id" => user_id})?
+        .filter_map(result_filter)
+        .collect())
+}
+
+crate fn update_password<T>(
+    conn: &mut T,
+    user_id: &str,
+    password: &str,
+    last_modified_by: &str,
+) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    match conn.prepare(*UPDATE_PASSWORD) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "password" => password,
+                "last_modified_by" => last_modified_by,
+                "user_id" => user_id,
+            })?;
+
+            if result.affected_rows() != 1 {
+                return Err(MoziasApiErrKind::InsertFailed.into());
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Replace the given user's password hash with one using the current Argon2
+/// parameters, returning `false` if the hash changed in the meantime.
+crate fn rehash_password(
+    pool: &Pool,
+    user_id: &str,
+    old_password: &str,
+    password: &str,
+) -> MoziasApiResult<bool> {
+    match pool.prepare(*REHASH_PASSWORD) {
+        Ok(mut stmt) => {
+            let result = stmt.execute(params! {
+                "password" => password,
+                "last_modified_by" => user_id,
+                "user_id" => user_id,
+                "old_password" => old_password,
+            })?;
+            Ok(result.affected_rows() == 1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e.into())
+        }
+    }
+}
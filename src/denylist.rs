@@ -0,0 +1,96 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Token Denylist
+//!
+//! Tokens are stateless, so revoking one before its `exp` means remembering its
+//! `jti` until then.  Revoked ids are kept in `mozias_token_denylist` and cached
+//! in memory so checking a token doesn't cost a query.  Every
+//! `MOZIAS_DENYLIST_SYNC_SECONDS` the table is pruned of entries whose token has
+//! expired anyway, and the cache is reloaded so revocations made by other
+//! instances are picked up.
+//!
+//! ```
+//! ```
+use crate::config::env_or;
+use crate::db::denylist as db;
+use crate::error::MoziasApiResult;
+use chrono::Utc;
+use mysql::Pool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_SYNC_SECONDS: u64 = 60;
+
+/// The denylist managed by rocket
+crate type SharedDenylist = Arc<Denylist>;
+
+/// Revoked token ids and the `exp` of the token each was revoked from
+#[derive(Debug, Default)]
+crate struct Denylist {
+    entries: RwLock<HashMap<String, i64>>,
+}
+
+impl Denylist {
+    /// Load the current denylist and start keeping it in sync.
+    crate fn start(pool: &Pool) -> MoziasApiResult<SharedDenylist> {
+        let denylist = Arc::new(Self::default());
+        denylist.reload(pool)?;
+
+        // A zero interval would spin the sync thread against the database
+        let sync_seconds = env_or("MOZIAS_DENYLIST_SYNC_SECONDS", DEFAULT_SYNC_SECONDS).max(1);
+        let pool = pool.clone();
+        let synced = Arc::clone(&denylist);
+        let _ = thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(sync_seconds));
+
+            if let Err(e) = db::prune_denied_tokens(&pool).and_then(|_| synced.reload(&pool)) {
+                eprintln!("{}", e);
+            }
+        });
+
+        Ok(denylist)
+    }
+
+    /// Has the token with the given id been revoked?
+    crate fn is_denied(&self, jti: &str) -> bool {
+        match self.entries.read() {
+            Ok(entries) => entries.contains_key(jti),
+            // Fail closed rather than let a revoked token through
+            Err(_) => true,
+        }
+    }
+
+    /// Revoke the token with the given id until its `exp`.
+    crate fn deny(
+        &self,
+        pool: &Pool,
+        jti: &str,
+        subject_id: &str,
+        exp: i64,
+        revoked_by: &str,
+    ) -> MoziasApiResult<()> {
+        db::insert_denied_token(pool, jti, subject_id, exp, revoked_by)?;
+        let mut entries = self.entries.write().map_err(|_| "denylist lock poisoned")?;
+        let _ = entries.insert(jti.to_string(), exp);
+        Ok(())
+    }
+
+    /// Merge in the stored entries and drop the expired ones.  Merging rather
+    /// than replacing keeps a `deny` that raced the query.
+    fn reload(&self, pool: &Pool) -> MoziasApiResult<()> {
+        let denied = db::denied_tokens(pool)?;
+        let now = Utc::now().timestamp();
+        let mut entries = self.entries.write().map_err(|_| "denylist lock poisoned")?;
+        entries.extend(denied);
+        entries.retain(|_, exp| *exp > now);
+        Ok(())
+    }
+}
@@ -10,8 +10,9 @@
 //!
 //! ```
 //! ```
+use crate::model::oauth::INVALID_CLIENT;
 use getset::Setters;
-use rocket::http::{ContentType, Status};
+use rocket::http::{ContentType, Header, Status};
 use rocket::request::Request;
 use rocket::response::{self, Responder, Response};
 use serde_derive::{Deserialize, Serialize};
@@ -20,6 +21,11 @@ use std::error::Error;
 use std::fmt;
 use std::io::Cursor;
 
+const WWW_AUTHENTICATE_HEADER: &str = "WWW-Authenticate";
+const BEARER_CHALLENGE: &str = r#"Bearer realm="mozias-api""#;
+const RETRY_AFTER_HEADER: &str = "Retry-After";
+const INSUFFICIENT_SCOPE: &str = "insufficient_scope";
+
 /// A result that includes a `mussh::Error`
 crate type MoziasApiResult<T> = Result<T, MoziasApiErr>;
 
@@ -28,6 +34,10 @@ crate type MoziasApiResult<T> = Result<T, MoziasApiErr>;
 crate struct ErrorResponse {
     #[set = "pub"]
     message: String,
+    // OAuth 2.0 error code (RFC 6749 section 5.2)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    error: Option<String>,
 }
 
 /// An error thrown by the mussh library
@@ -37,6 +47,13 @@ crate struct MoziasApiErr {
     inner: MoziasApiErrKind,
 }
 
+impl MoziasApiErr {
+    /// The kind of error
+    crate fn kind(&self) -> &MoziasApiErrKind {
+        &self.inner
+    }
+}
+
 impl Error for MoziasApiErr {
     fn description(&self) -> &str {
         "MoziasApi Error"
@@ -61,19 +78,61 @@ impl fmt::Display for MoziasApiErr {
 impl<'r> Responder<'r> for MoziasApiErr {
     fn respond_to(self, _: &Request<'_>) -> response::Result<'r> {
         let status = match self.inner {
+            MoziasApiErrKind::BadRequest => Status::BadRequest,
+            MoziasApiErrKind::Conflict => Status::Conflict,
+            MoziasApiErrKind::Forbidden => Status::Forbidden,
+            MoziasApiErrKind::InsufficientScope(_) => Status::Forbidden,
+            MoziasApiErrKind::NotFound => Status::NotFound,
+            MoziasApiErrKind::OAuth(INVALID_CLIENT) => Status::Unauthorized,
+            MoziasApiErrKind::OAuth(_) => Status::BadRequest,
+            MoziasApiErrKind::TooManyRequests(_) => Status::TooManyRequests,
             MoziasApiErrKind::Unauthorized => Status::Unauthorized,
             _ => Status::InternalServerError,
         };
 
         let mut err_response = ErrorResponse::default();
         let _ = err_response.set_message(self.inner.description().to_string());
+
+        match self.inner {
+            MoziasApiErrKind::OAuth(error) => {
+                let _ = err_response.set_error(Some(error.to_string()));
+            }
+            MoziasApiErrKind::InsufficientScope(_) => {
+                let _ = err_response.set_error(Some(INSUFFICIENT_SCOPE.to_string()));
+            }
+            _ => {}
+        }
         let err_json = json!(err_response);
 
-        Response::build()
+        let mut builder = Response::build();
+        let _ = builder
             .status(status)
             .sized_body(Cursor::new(err_json.to_string()))
-            .header(ContentType::JSON)
-            .ok()
+            .header(ContentType::JSON);
+
+        match self.inner {
+            MoziasApiErrKind::Unauthorized => {
+                let _ = builder.header(Header::new(WWW_AUTHENTICATE_HEADER, BEARER_CHALLENGE));
+            }
+            MoziasApiErrKind::InsufficientScope(scope) => {
+                // RFC 6750 section 3.1, where `scope` is optional
+                let challenge = if scope.is_empty() {
+                    format!(r#"{}, error="{}""#, BEARER_CHALLENGE, INSUFFICIENT_SCOPE)
+                } else {
+                    format!(
+                        r#"{}, error="{}", scope="{}""#,
+                        BEARER_CHALLENGE, INSUFFICIENT_SCOPE, scope
+                    )
+                };
+                let _ = builder.header(Header::new(WWW_AUTHENTICATE_HEADER, challenge));
+            }
+            MoziasApiErrKind::TooManyRequests(retry_after) => {
+                let _ = builder.header(Header::new(RETRY_AFTER_HEADER, retry_after.to_string()));
+            }
+            _ => {}
+        }
+
+        builder.ok()
     }
 }
 
@@ -118,15 +177,22 @@ external_error!(std::env::VarError, MoziasApiErrKind::Var);
 #[allow(variant_size_differences)]
 crate enum MoziasApiErrKind {
     Argon2(argon2::Error),
+    BadRequest,
     Clap(clap::Error),
+    Conflict,
+    Forbidden,
     Header,
     InsertFailed,
+    InsufficientScope(&'static str),
     Io(std::io::Error),
     JsonWebToken(jsonwebtoken::errors::Error),
     Launch(rocket::error::LaunchError),
     Mysql(mysql::Error),
     NoInsertId,
+    NotFound,
+    OAuth(&'static str),
     Str(String),
+    TooManyRequests(i64),
     Unauthorized,
     UuidParse(uuid::Error),
     Var(std::env::VarError),
@@ -136,15 +202,22 @@ impl Error for MoziasApiErrKind {
     fn description(&self) -> &str {
         match self {
             Self::Argon2(inner) => inner.description(),
+            Self::BadRequest => "bad request",
             Self::Clap(inner) => inner.description(),
+            Self::Conflict => "conflict",
+            Self::Forbidden => "forbidden",
             Self::Header => "invalid header",
             Self::InsertFailed => "insert failed",
+            Self::InsufficientScope(_) => "insufficient scope",
             Self::Io(inner) => inner.description(),
             Self::JsonWebToken(inner) => inner.description(),
             Self::Launch(inner) => inner.description(),
             Self::Mysql(inner) => inner.description(),
             Self::NoInsertId => "no insert id found",
+            Self::NotFound => "not found",
+            Self::OAuth(error) => error,
             Self::Str(inner) => &inner[..],
+            Self::TooManyRequests(_) => "too many requests",
             Self::Unauthorized => "unauthorized",
             Self::UuidParse(inner) => inner.description(),
             Self::Var(inner) => inner.description(),
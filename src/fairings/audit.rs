@@ -0,0 +1,59 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Audit Fairing
+//!
+//! Records every request made with an impersonation token in
+//! `mozias_impersonation_audit`.  Unlike telemetry this doesn't depend on the
+//! request carrying an `x-request-id`.
+//!
+//! ```
+//! ```
+use crate::db;
+use crate::error::MoziasApiResult;
+use crate::fairings::telemetry::MOZIAS_UUID_HEADER;
+use crate::guards::auth::RequestIdentity;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Request, Response};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct Audit;
+
+impl Audit {
+    fn response(req: &Request<'_>, resp: &Response<'_>) -> MoziasApiResult<()> {
+        let identity = req.local_cache(RequestIdentity::default);
+
+        if let (Some(user_id), Some(actor_id)) = (identity.user_id(), identity.actor_id()) {
+            db::audit::insert_impersonation_audit(
+                &db::get_pool()?,
+                actor_id,
+                user_id,
+                req.headers().get_one(MOZIAS_UUID_HEADER),
+                &req.method().to_string(),
+                req.uri().path(),
+                resp.status().code,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Fairing for Audit {
+    fn info(&self) -> Info {
+        Info {
+            name: "Impersonation Audit",
+            kind: Kind::Response,
+        }
+    }
+
+    fn on_response(&self, request: &Request<'_>, response: &mut Response<'_>) {
+        if let Err(e) = Self::response(request, response) {
+            eprintln!("{}", e);
+        }
+    }
+}
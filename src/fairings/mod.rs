@@ -10,4 +10,5 @@
 //!
 //! ```
 //! ```
+crate mod audit;
 crate mod telemetry;
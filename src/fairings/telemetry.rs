@@ -12,6 +12,7 @@
 //! ```
 use crate::db;
 use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::auth::RequestIdentity;
 use getset::{Getters, Setters};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::{Cookie, Header};
@@ -20,7 +21,7 @@ use std::fmt;
 use std::time::Instant;
 use uuid::Uuid;
 
-const MOZIAS_UUID_HEADER: &str = "x-request-id";
+crate const MOZIAS_UUID_HEADER: &str = "x-request-id";
 
 #[derive(Clone, Copy)]
 crate enum DirectionType {
@@ -67,6 +68,13 @@ crate struct Telemetry {
     #[get = "crate"]
     #[set]
     content_type: Option<String>,
+    #[get = "crate"]
+    #[set]
+    user_id: Option<String>,
+    // Set when an administrator is impersonating `user_id`
+    #[get = "crate"]
+    #[set]
+    actor_id: Option<String>,
 }
 
 impl Telemetry {
@@ -100,6 +108,9 @@ impl Telemetry {
         let resp_headers: Vec<Header<'_>> = resp.headers().iter().map(|h| h).collect();
         let resp_cookies: Vec<Cookie<'_>> = resp.cookies().to_vec();
 
+        // Left by the authentication guards, if any ran
+        let identity = req.local_cache(RequestIdentity::default);
+
         // Grab the request local telemetry and enhance with info for persistence
         let orig_telemetry = req.local_cache(Self::default);
 
@@ -111,6 +122,8 @@ impl Telemetry {
         let _ = telemetry.set_real_ip(real_ip);
         let _ = telemetry.set_status(status);
         let _ = telemetry.set_content_type(content_type);
+        let _ = telemetry.set_user_id(identity.user_id().clone());
+        let _ = telemetry.set_actor_id(identity.actor_id().clone());
 
         let elapsed = if let Some(duration) = telemetry.start.map(|st| st.elapsed()) {
             duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
@@ -0,0 +1,344 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Authentication Guards
+//!
+//! ```
+//! ```
+use crate::db::auth as db;
+use crate::db::{api_key, role, session};
+use crate::denylist::SharedDenylist;
+use crate::error::{MoziasApiErr, MoziasApiErrKind, MoziasApiResult};
+use crate::model::auth::TokenType;
+use crate::model::role::{Role, RoleName};
+use crate::model::scope::{self, ScopeName};
+use crate::{secret, token};
+use getset::Getters;
+use mysql::Pool;
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::{Outcome, State};
+use std::marker::PhantomData;
+
+const AUTHORIZATION_HEADER: &str = "Authorization";
+const BEARER_PREFIX: &str = "Bearer ";
+const API_KEY_PREFIX: &str = "ApiKey ";
+
+/// A request carrying a valid bearer access token that was not delegated to a
+/// client.  Delegated tokens are limited to routes that require one of their
+/// scopes via `RequireScope`.
+#[derive(Clone, Debug, Eq, Getters, PartialEq)]
+crate struct AuthenticatedUser {
+    /// The username the token was issued to
+    #[get = "pub"]
+    sub: String,
+    /// The user id the token was issued to
+    #[get = "pub"]
+    aid: String,
+    /// The session the token was issued to
+    #[get = "pub"]
+    sid: String,
+    /// The roles granted to the user when the token was issued
+    #[get = "pub"]
+    rol: Vec<Role>,
+    /// The user id of the administrator impersonating the user, if any
+    #[get = "pub"]
+    act: Option<String>,
+}
+
+impl AuthenticatedUser {
+    crate fn new(sub: String, aid: String, sid: String, rol: Vec<Role>) -> Self {
+        Self {
+            sub,
+            aid,
+            sid,
+            rol,
+            act: None,
+        }
+    }
+
+    /// Check the bearer token, returning the user and, for tokens issued to a
+    /// client, the scopes the client was granted.
+    fn authenticate(request: &Request<'_>) -> MoziasApiResult<(Self, Option<Vec<String>>)> {
+        let header = request
+            .headers()
+            .get_one(AUTHORIZATION_HEADER)
+            .ok_or_else(|| MoziasApiErrKind::Unauthorized)?;
+
+        if !header.starts_with(BEARER_PREFIX) {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let claims = token::decode(header[BEARER_PREFIX.len()..].trim())
+            .map_err(|_| MoziasApiErrKind::Unauthorized)?;
+
+        // Refresh tokens are only good for the refresh endpoint, tfa tokens
+        // are only good for the tfa exchange, and service tokens have no user
+        if *claims.typ() != TokenType::Access || *claims.tfa() || claims.aid().is_empty() {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let denylist = request
+            .guard::<State<'_, SharedDenylist>>()
+            .succeeded()
+            .ok_or_else(|| MoziasApiErr::from("cannot get denylist"))?;
+
+        if denylist.is_denied(claims.jti()) {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        // Disabling an account has to take effect before the token expires
+        let pool = request
+            .guard::<State<'_, Pool>>()
+            .succeeded()
+            .ok_or_else(|| MoziasApiErr::from("cannot get pool"))?;
+
+        if db::is_user_disabled(&*pool, claims.aid())? {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        // Revoking a session revokes its access tokens too, as introspection reports
+        if !claims.sid().is_empty() && !session::session_exists(&*pool, claims.sid(), claims.aid())?
+        {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let scopes = if claims.cid().is_empty() {
+            None
+        } else {
+            Some(scope::parse(claims.scope()))
+        };
+
+        let user = Self {
+            sub: claims.sub().clone(),
+            aid: claims.aid().clone(),
+            sid: claims.sid().clone(),
+            rol: claims.rol().clone(),
+            act: claims.act().as_ref().map(|actor| actor.sub().clone()),
+        };
+        user.record_identity(request);
+        Ok((user, scopes))
+    }
+
+    crate fn has_role(&self, name: &str) -> bool {
+        self.rol.iter().any(|role| role.name() == name)
+    }
+
+    /// Leave who made the request, and who they were acting as, for the fairings.
+    crate fn record_identity(&self, request: &Request<'_>) {
+        let _ = request.local_cache(|| RequestIdentity {
+            user_id: Some(self.aid.clone()),
+            actor_id: self.act.clone(),
+        });
+    }
+}
+
+/// The user a request was authenticated as, and the administrator
+/// impersonating them if any
+#[derive(Clone, Debug, Default, Eq, Getters, PartialEq)]
+crate struct RequestIdentity {
+    #[get = "pub"]
+    user_id: Option<String>,
+    #[get = "pub"]
+    actor_id: Option<String>,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for AuthenticatedUser {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match Self::authenticate(request) {
+            Ok((user, None)) => Outcome::Success(user),
+            Ok((_, Some(_))) => {
+                // No scope grants a client a route meant for the user's own
+                // tokens, so the challenge names none
+                let _ = request.local_cache(|| MissingScope(Some(NO_SCOPE)));
+                Outcome::Failure((
+                    Status::Forbidden,
+                    MoziasApiErrKind::InsufficientScope(NO_SCOPE).into(),
+                ))
+            }
+            Err(e) => Outcome::Failure((Status::Unauthorized, e)),
+        }
+    }
+}
+
+/// A request carrying a valid bearer access token the user obtained themselves,
+/// rather than one an administrator minted to impersonate them
+#[derive(Clone, Debug, Eq, PartialEq)]
+crate struct OwnAccount {
+    user: AuthenticatedUser,
+}
+
+impl OwnAccount {
+    /// The user acting on their own account
+    crate fn user(&self) -> &AuthenticatedUser {
+        &self.user
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for OwnAccount {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let user = request.guard::<AuthenticatedUser>()?;
+
+        if user.act().is_none() {
+            Outcome::Success(Self { user })
+        } else {
+            Outcome::Failure((Status::Forbidden, MoziasApiErrKind::Forbidden.into()))
+        }
+    }
+}
+
+/// A request carrying a valid bearer access token that was granted role `R`.
+/// Impersonation tokens never qualify, since they carry the impersonated
+/// user's roles under the impersonated user's identity.
+#[derive(Clone, Debug, Eq, PartialEq)]
+crate struct RequireRole<R: RoleName> {
+    user: AuthenticatedUser,
+    role: PhantomData<R>,
+}
+
+impl<R: RoleName> RequireRole<R> {
+    /// The authenticated user holding the role
+    crate fn user(&self) -> &AuthenticatedUser {
+        &self.user
+    }
+}
+
+impl<'a, 'r, R: RoleName> FromRequest<'a, 'r> for RequireRole<R> {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let user = request.guard::<OwnAccount>()?.user;
+
+        if user.has_role(R::NAME) {
+            Outcome::Success(Self {
+                user,
+                role: PhantomData,
+            })
+        } else {
+            Outcome::Failure((Status::Forbidden, MoziasApiErrKind::Forbidden.into()))
+        }
+    }
+}
+
+/// A request carrying either a personal API key or a valid bearer access token
+#[derive(Clone, Debug, Eq, Getters, PartialEq)]
+crate struct ApiUser {
+    /// The user the key or token belongs to
+    #[get = "pub"]
+    user: AuthenticatedUser,
+    /// The scopes of the API key or delegated token, `None` for first-party
+    /// tokens, which may do anything the user can
+    #[get = "pub"]
+    scopes: Option<Vec<String>>,
+}
+
+impl ApiUser {
+    crate fn has_scope(&self, name: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .map_or(true, |scopes| scopes.iter().any(|scope| scope == name))
+    }
+
+    fn authenticate_api_key(request: &Request<'_>, key: &str) -> MoziasApiResult<Self> {
+        let pool = request
+            .guard::<State<'_, Pool>>()
+            .succeeded()
+            .ok_or_else(|| MoziasApiErr::from("cannot get pool"))?;
+        let key_vec = api_key::api_key_by_hash(&*pool, &secret::hash(key))?;
+        let (id, user_id, username, scope) = key_vec
+            .first()
+            .ok_or_else(|| MoziasApiErrKind::Unauthorized)?;
+
+        api_key::touch_api_key(&*pool, id)?;
+
+        let user = AuthenticatedUser::new(
+            username.clone(),
+            user_id.clone(),
+            String::new(),
+            role::find_roles_by_user_id(&*pool, user_id)?,
+        );
+        user.record_identity(request);
+
+        Ok(Self {
+            user,
+            scopes: Some(scope::parse(scope)),
+        })
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ApiUser {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.headers().get_one(AUTHORIZATION_HEADER) {
+            Some(header) if header.starts_with(API_KEY_PREFIX) => {
+                match Self::authenticate_api_key(request, header[API_KEY_PREFIX.len()..].trim()) {
+                    Ok(user) => Outcome::Success(user),
+                    Err(e) => Outcome::Failure((Status::Unauthorized, e)),
+                }
+            }
+            _ => match AuthenticatedUser::authenticate(request) {
+                Ok((user, scopes)) => Outcome::Success(Self { user, scopes }),
+                Err(e) => Outcome::Failure((Status::Unauthorized, e)),
+            },
+        }
+    }
+}
+
+/// The scope reported when a client token is refused by a route no scope opens
+const NO_SCOPE: &str = "";
+
+/// The scope a `RequireScope` guard found missing, for the 403 catcher
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct MissingScope(Option<&'static str>);
+
+impl MissingScope {
+    crate fn scope(self) -> Option<&'static str> {
+        self.0
+    }
+}
+
+/// A request carrying an API key or bearer access token that was granted scope `S`
+#[derive(Clone, Debug, Eq, PartialEq)]
+crate struct RequireScope<S: ScopeName> {
+    user: ApiUser,
+    scope: PhantomData<S>,
+}
+
+impl<S: ScopeName> RequireScope<S> {
+    /// The authenticated user holding the scope
+    crate fn user(&self) -> &AuthenticatedUser {
+        self.user.user()
+    }
+}
+
+impl<'a, 'r, S: ScopeName> FromRequest<'a, 'r> for RequireScope<S> {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let user = request.guard::<ApiUser>()?;
+
+        if user.has_scope(S::NAME) {
+            Outcome::Success(Self {
+                user,
+                scope: PhantomData,
+            })
+        } else {
+            // Catchers never see the guard's error, so leave it where the 403 catcher can find it
+            let _ = request.local_cache(|| MissingScope(Some(S::NAME)));
+            Outcome::Failure((
+                Status::Forbidden,
+                MoziasApiErrKind::InsufficientScope(S::NAME).into(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Client Guards
+//!
+//! ```
+//! ```
+use crate::db::client as db;
+use crate::error::{MoziasApiErr, MoziasApiErrKind, MoziasApiResult};
+use crate::secret;
+use getset::Getters;
+use mysql::Pool;
+use ring::constant_time::verify_slices_are_equal;
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::{Outcome, State};
+
+const USER_AGENT_HEADER: &str = "User-Agent";
+const UNKNOWN_USER_AGENT: &str = "unknown";
+const AUTHORIZATION_HEADER: &str = "Authorization";
+const BASIC_PREFIX: &str = "Basic ";
+
+/// The device a request came from
+#[derive(Clone, Debug, Eq, Getters, PartialEq)]
+crate struct ClientInfo {
+    #[get = "pub"]
+    user_agent: String,
+    #[get = "pub"]
+    ip: Option<String>,
+}
+
+impl ClientInfo {
+    crate fn ip_str(&self) -> Option<&str> {
+        self.ip.as_ref().map(String::as_str)
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientInfo {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let user_agent = request
+            .headers()
+            .get_one(USER_AGENT_HEADER)
+            .unwrap_or(UNKNOWN_USER_AGENT)
+            .to_string();
+        let ip = request
+            .real_ip()
+            .or_else(|| request.remote().map(|r| r.ip()))
+            .map(|ip| ip.to_string());
+
+        Outcome::Success(Self { user_agent, ip })
+    }
+}
+
+/// A request from a registered client, authenticated with HTTP Basic
+/// `client_id:client_secret` (RFC 6749 section 2.3.1)
+#[derive(Clone, Debug, Eq, Getters, PartialEq)]
+crate struct AuthenticatedClient {
+    #[get = "pub"]
+    client_id: String,
+}
+
+impl AuthenticatedClient {
+    fn authenticate(request: &Request<'_>) -> MoziasApiResult<Self> {
+        let header = request
+            .headers()
+            .get_one(AUTHORIZATION_HEADER)
+            .ok_or_else(|| MoziasApiErrKind::Unauthorized)?;
+
+        if !header.starts_with(BASIC_PREFIX) {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let decoded = base64::decode(header[BASIC_PREFIX.len()..].trim())
+            .map_err(|_| MoziasApiErrKind::Unauthorized)?;
+        let credentials = String::from_utf8(decoded).map_err(|_| MoziasApiErrKind::Unauthorized)?;
+        let mut parts = credentials.splitn(2, ':');
+        let client_id = parts.next().unwrap_or("");
+        let client_secret = parts.next().ok_or_else(|| MoziasApiErrKind::Unauthorized)?;
+
+        let pool = request
+            .guard::<State<'_, Pool>>()
+            .succeeded()
+            .ok_or_else(|| MoziasApiErr::from("cannot get pool"))?;
+        let hash_vec = db::client_secret_hash_by_id(&*pool, client_id)?;
+        let given_hash = secret::hash(client_secret);
+
+        match hash_vec.first() {
+            Some(Some(hash))
+                if verify_slices_are_equal(hash.as_bytes(), given_hash.as_bytes()).is_ok() =>
+            {
+                Ok(Self {
+                    client_id: client_id.to_string(),
+                })
+            }
+            _ => Err(MoziasApiErrKind::Unauthorized.into()),
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for AuthenticatedClient {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match Self::authenticate(request) {
+            Ok(client) => Outcome::Success(client),
+            Err(e) => Outcome::Failure((Status::Unauthorized, e)),
+        }
+    }
+}
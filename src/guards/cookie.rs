@@ -0,0 +1,168 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Cookie Session Guards
+//!
+//! Browser logins get an encrypted (private) session cookie plus a readable
+//! CSRF cookie.  Browsers attach cookies to cross-site requests too, so unsafe
+//! methods must also echo the CSRF token in `X-CSRF-Token`, which another site
+//! cannot read.
+//!
+//! ```
+//! ```
+use crate::config;
+use crate::db::auth as db;
+use crate::db::{role, session};
+use crate::error::{MoziasApiErr, MoziasApiErrKind, MoziasApiResult};
+use crate::guards::auth::AuthenticatedUser;
+use crate::model::session::SessionCookie;
+use chrono::Utc;
+use mysql::Pool;
+use ring::constant_time::verify_slices_are_equal;
+use rocket::http::{Cookie, Method, SameSite, Status};
+use rocket::request::{self, FromRequest, Request};
+use rocket::{Outcome, State};
+
+crate const SESSION_COOKIE: &str = "mozias_session";
+crate const CSRF_COOKIE: &str = "mozias_csrf";
+const CSRF_HEADER: &str = "X-CSRF-Token";
+const AUTHORIZATION_HEADER: &str = "Authorization";
+
+/// Build a session or CSRF cookie.  Only the CSRF cookie is readable by page scripts.
+crate fn build_cookie(name: &'static str, value: String, http_only: bool) -> Cookie<'static> {
+    Cookie::build(name, value)
+        .path("/")
+        .http_only(http_only)
+        .same_site(SameSite::Strict)
+        .secure(config::env_or("MOZIAS_SECURE_COOKIES", true))
+        .finish()
+}
+
+/// A request from a logged in browser, or one carrying a valid bearer access token
+#[derive(Clone, Debug, Eq, PartialEq)]
+crate struct SessionUser {
+    user: AuthenticatedUser,
+}
+
+impl SessionUser {
+    /// The user the session or token belongs to
+    crate fn user(&self) -> &AuthenticatedUser {
+        &self.user
+    }
+
+    fn authenticate(
+        request: &Request<'_>,
+        session_cookie: &SessionCookie,
+    ) -> MoziasApiResult<Self> {
+        if *session_cookie.exp() < Utc::now().timestamp() {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        // Logging out elsewhere or disabling the account has to end the session
+        let pool = request
+            .guard::<State<'_, Pool>>()
+            .succeeded()
+            .ok_or_else(|| MoziasApiErr::from("cannot get pool"))?;
+        let sid = session_cookie.sid();
+        let aid = session_cookie.aid();
+
+        if !session::session_exists(&*pool, sid, aid)? || db::is_user_disabled(&*pool, aid)? {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let user = AuthenticatedUser::new(
+            session_cookie.sub().clone(),
+            aid.clone(),
+            sid.clone(),
+            role::find_roles_by_user_id(&*pool, aid)?,
+        );
+        user.record_identity(request);
+        Ok(Self { user })
+    }
+}
+
+fn is_safe(method: Method) -> bool {
+    match method {
+        Method::Get | Method::Head | Method::Options => true,
+        _ => false,
+    }
+}
+
+fn has_valid_csrf_token(request: &Request<'_>, session_cookie: &SessionCookie) -> bool {
+    request
+        .headers()
+        .get_one(CSRF_HEADER)
+        .map_or(false, |token| {
+            verify_slices_are_equal(token.as_bytes(), session_cookie.csrf().as_bytes()).is_ok()
+        })
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for SessionUser {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        // An explicit bearer token can't be sent cross-site, so it wins over the cookie
+        if request.headers().contains(AUTHORIZATION_HEADER) {
+            let user = request.guard::<AuthenticatedUser>()?;
+            return Outcome::Success(Self { user });
+        }
+
+        let session_cookie: SessionCookie = match request
+            .cookies()
+            .get_private(SESSION_COOKIE)
+            .and_then(|cookie| serde_json::from_str(cookie.value()).ok())
+        {
+            Some(session_cookie) => session_cookie,
+            None => {
+                return Outcome::Failure((
+                    Status::Unauthorized,
+                    MoziasApiErrKind::Unauthorized.into(),
+                ))
+            }
+        };
+
+        if !is_safe(request.method()) && !has_valid_csrf_token(request, &session_cookie) {
+            return Outcome::Failure((Status::Forbidden, MoziasApiErrKind::Forbidden.into()));
+        }
+
+        match Self::authenticate(request, &session_cookie) {
+            Ok(user) => Outcome::Success(user),
+            Err(e) => Outcome::Failure((Status::Unauthorized, e)),
+        }
+    }
+}
+
+/// A `SessionUser` acting on their own account, rather than an administrator
+/// impersonating them.  Routes that change credentials use this, since
+/// impersonation is for seeing what the user sees, never for taking over the
+/// account.
+#[derive(Clone, Debug, Eq, PartialEq)]
+crate struct OwnSession {
+    user: AuthenticatedUser,
+}
+
+impl OwnSession {
+    /// The user acting on their own account
+    crate fn user(&self) -> &AuthenticatedUser {
+        &self.user
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for OwnSession {
+    type Error = MoziasApiErr;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let user = request.guard::<SessionUser>()?.user;
+
+        if user.act().is_none() {
+            Outcome::Success(Self { user })
+        } else {
+            Outcome::Failure((Status::Forbidden, MoziasApiErrKind::Forbidden.into()))
+        }
+    }
+}
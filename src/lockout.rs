@@ -0,0 +1,166 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Login Lockout
+//!
+//! Failed logins are counted per username and per source ip.  Once either
+//! count reaches its threshold, further attempts are locked out for
+//! `base_seconds`, doubling with every additional failure up to `max_seconds`.
+//! Failed TOTP codes are counted per user the same way, so a password alone
+//! doesn't buy unlimited guesses at the code.  Password reset requests are
+//! counted per username and per ip whether or not they succeed, since the
+//! response doesn't say.
+//!
+//! A successful login only clears the username count.  The ip count is left to
+//! expire with its window, since otherwise an attacker could reset it between
+//! guesses by logging in to an account of their own.  A NAT shared by many
+//! users may need a higher `MOZIAS_LOCKOUT_IP_THRESHOLD`.
+//!
+//! ```
+//! ```
+use crate::config::env_or;
+use crate::db::in_txn;
+use crate::db::lockout as db;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use chrono::Utc;
+use mysql::Pool;
+
+// Cap the doubling so the shift can't overflow
+const MAX_DOUBLINGS: u64 = 30;
+
+/// Lockout thresholds, read from the environment
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+crate struct Lockout {
+    username_threshold: u64,
+    ip_threshold: u64,
+    tfa_threshold: u64,
+    reset_threshold: u64,
+    base_seconds: i64,
+    max_seconds: i64,
+    window_seconds: i64,
+}
+
+impl Default for Lockout {
+    fn default() -> Self {
+        Self {
+            username_threshold: 5,
+            ip_threshold: 20,
+            tfa_threshold: 5,
+            reset_threshold: 3,
+            base_seconds: 30,
+            max_seconds: 3600,
+            window_seconds: 3600,
+        }
+    }
+}
+
+impl Lockout {
+    crate fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            username_threshold: env_or(
+                "MOZIAS_LOCKOUT_USERNAME_THRESHOLD",
+                defaults.username_threshold,
+            ),
+            ip_threshold: env_or("MOZIAS_LOCKOUT_IP_THRESHOLD", defaults.ip_threshold),
+            tfa_threshold: env_or("MOZIAS_LOCKOUT_TFA_THRESHOLD", defaults.tfa_threshold),
+            reset_threshold: env_or("MOZIAS_LOCKOUT_RESET_THRESHOLD", defaults.reset_threshold),
+            base_seconds: env_or("MOZIAS_LOCKOUT_BASE_SECONDS", defaults.base_seconds),
+            max_seconds: env_or("MOZIAS_LOCKOUT_MAX_SECONDS", defaults.max_seconds),
+            window_seconds: env_or("MOZIAS_LOCKOUT_WINDOW_SECONDS", defaults.window_seconds),
+        }
+    }
+
+    /// Fail with `TooManyRequests` if the username or ip is currently locked out.
+    crate fn check(pool: &Pool, username: &str, ip: Option<&str>) -> MoziasApiResult<()> {
+        let retry_after = db::locked_until(pool, username, ip)? - Utc::now().timestamp();
+        Self::check_retry_after(retry_after)
+    }
+
+    /// Fail with `TooManyRequests` if TOTP codes for the given user are currently locked out.
+    crate fn check_tfa(pool: &Pool, user_id: &str) -> MoziasApiResult<()> {
+        let retry_after =
+            db::locked_until_by_kind(pool, db::TFA, user_id)? - Utc::now().timestamp();
+        Self::check_retry_after(retry_after)
+    }
+
+    /// Fail with `TooManyRequests` if password resets for the username or ip are
+    /// currently locked out.
+    crate fn check_reset(
+        pool: &Pool,
+        username: &str,
+        ip: Option<&str>,
+    ) -> MoziasApiResult<()> {
+        let mut locked_until = db::locked_until_by_kind(pool, db::RESET, username)?;
+
+        if let Some(ip) = ip {
+            locked_until = locked_until.max(db::locked_until_by_kind(pool, db::RESET_IP, ip)?);
+        }
+
+        Self::check_retry_after(locked_until - Utc::now().timestamp())
+    }
+
+    fn check_retry_after(retry_after: i64) -> MoziasApiResult<()> {
+        if retry_after > 0 {
+            Err(MoziasApiErrKind::TooManyRequests(retry_after).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    crate fn record_failure(&self, username: &str, ip: Option<&str>) -> MoziasApiResult<()> {
+        self.record(db::USERNAME, username, self.username_threshold)?;
+
+        if let Some(ip) = ip {
+            self.record(db::IP, ip, self.ip_threshold)?;
+        }
+        Ok(())
+    }
+
+    /// Clear the username's failures, leaving the ip's to expire.
+    crate fn record_success(pool: &Pool, username: &str) -> MoziasApiResult<()> {
+        let _ = db::clear_failures(pool, db::USERNAME, username)?;
+        Ok(())
+    }
+
+    crate fn record_tfa_failure(&self, user_id: &str) -> MoziasApiResult<()> {
+        self.record(db::TFA, user_id, self.tfa_threshold)
+    }
+
+    crate fn record_tfa_success(pool: &Pool, user_id: &str) -> MoziasApiResult<()> {
+        let _ = db::clear_failures(pool, db::TFA, user_id)?;
+        Ok(())
+    }
+
+    crate fn record_reset(&self, username: &str, ip: Option<&str>) -> MoziasApiResult<()> {
+        self.record(db::RESET, username, self.reset_threshold)?;
+
+        if let Some(ip) = ip {
+            self.record(db::RESET_IP, ip, self.ip_threshold)?;
+        }
+        Ok(())
+    }
+
+    fn record(&self, kind: &str, identifier: &str, threshold: u64) -> MoziasApiResult<()> {
+        // The upsert holds the row until commit, so concurrent failures each
+        // see their own count
+        in_txn(|txn| {
+            let failures = db::record_failure(txn, kind, identifier, self.window_seconds)?;
+
+            if failures >= threshold {
+                let doublings = (failures - threshold).min(MAX_DOUBLINGS);
+                let lockout = self
+                    .base_seconds
+                    .saturating_mul(1 << doublings)
+                    .min(self.max_seconds);
+                db::lock(txn, kind, identifier, Utc::now().timestamp() + lockout)?;
+            }
+            Ok(())
+        })
+    }
+}
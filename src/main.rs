@@ -46,13 +46,25 @@ use clap::ErrorKind;
 use std::error::Error;
 use std::process;
 
+mod catchers;
+mod config;
 mod cors;
 mod db;
+mod denylist;
 mod error;
 mod fairings;
+mod guards;
+mod keys;
+mod lockout;
 mod model;
+mod notify;
+mod password;
+mod pkce;
 mod routes;
 mod run;
+mod secret;
+mod token;
+mod totp;
 
 /// mozias-api entry point
 fn main() {
@@ -0,0 +1,71 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! API Key Models
+//!
+//! ```
+//! ```
+use chrono::NaiveDateTime;
+use getset::{Getters, Setters};
+use serde_derive::{Deserialize, Serialize};
+
+/// API key creation request
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct ApiKeyRequest {
+    /// Something to recognize the key by, e.g. the script or pipeline using it
+    #[get = "pub"]
+    name: String,
+    #[serde(default)]
+    #[get = "pub"]
+    scopes: Vec<String>,
+    /// When the key stops working, or never if absent
+    #[serde(default)]
+    #[get = "pub"]
+    expires: Option<NaiveDateTime>,
+}
+
+/// A personal API key.  The raw `key` is only ever present in the response to creating it.
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize, Setters)]
+crate struct ApiKey {
+    #[get = "pub"]
+    id: String,
+    #[get = "pub"]
+    name: String,
+    #[get = "pub"]
+    scopes: Vec<String>,
+    #[get = "pub"]
+    expires: Option<NaiveDateTime>,
+    #[get = "pub"]
+    last_used: Option<NaiveDateTime>,
+    #[get = "pub"]
+    created_date: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    key: Option<String>,
+}
+
+impl ApiKey {
+    crate fn new(
+        id: String,
+        name: String,
+        scopes: Vec<String>,
+        expires: Option<NaiveDateTime>,
+        last_used: Option<NaiveDateTime>,
+        created_date: NaiveDateTime,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            scopes,
+            expires,
+            last_used,
+            created_date,
+            key: None,
+        }
+    }
+}
@@ -10,10 +10,13 @@
 //!
 //! ```
 //! ```
+use crate::model::role::Role;
 use chrono::{NaiveDateTime, Utc};
 use getset::{Getters, Setters};
 use lazy_static::lazy_static;
+use rocket::FromForm;
 use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
 
 crate const SECONDS_PER_MINUTE: i64 = 60;
 crate const SECONDS_PER_HOUR: i64 = SECONDS_PER_MINUTE * 60;
@@ -90,9 +93,6 @@ crate struct UserProfile {
     user_id: String,
     #[get = "pub"]
     #[set = "pub"]
-    refresh_token: Option<String>,
-    #[get = "pub"]
-    #[set = "pub"]
     created_by: String,
     #[get = "pub"]
     #[set = "pub"]
@@ -110,7 +110,6 @@ impl Default for UserProfile {
         Self {
             id: String::new(),
             user_id: String::new(),
-            refresh_token: None,
             created_by: String::new(),
             created_date: NaiveDateTime::from_timestamp(Utc::now().timestamp(), 0),
             last_modified_by: String::new(),
@@ -132,31 +131,226 @@ crate struct Credentials {
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
 crate struct TokenResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    refresh_token: Option<String>,
+    // Limited token to exchange at `/auth/tfa/token` when 2FA is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    tfa_token: Option<String>,
+}
+
+/// A TOTP code
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct TfaCode {
+    #[get = "pub"]
+    code: String,
+}
+
+/// Two-factor token exchange request
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct TfaTokenRequest {
+    /// The limited token handed out by `/auth/token`
+    #[get = "pub"]
+    tfa_token: String,
+    /// The current TOTP code
+    #[get = "pub"]
+    code: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct TfaEnrollment {
+    #[set = "pub"]
+    secret: String,
     #[set = "pub"]
+    otpauth_uri: String,
+}
+
+/// Refresh token exchange request
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct RefreshRequest {
+    /// The refresh token handed out by `/auth/token`
+    #[get = "pub"]
     refresh_token: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct AccessTokenResponse {
+    #[set = "pub"]
+    access_token: String,
+    #[set = "pub"]
+    token_type: String,
+    #[set = "pub"]
+    expires_in: i64,
+    // The rotated refresh token that replaces the one presented
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    scope: Option<String>,
+    // OpenID Connect ID token, for authorization code grants with the `openid` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    id_token: Option<String>,
+}
+
+impl Default for AccessTokenResponse {
+    fn default() -> Self {
+        Self {
+            access_token: String::new(),
+            token_type: "Bearer".to_string(),
+            expires_in: 0,
+            refresh_token: None,
+            scope: None,
+            id_token: None,
+        }
+    }
+}
+
+/// A token an administrator wants revoked before it expires
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct TokenRevocation {
+    #[get = "pub"]
+    token: String,
+}
+
+/// Token introspection request (RFC 7662)
+#[derive(Clone, Debug, Eq, FromForm, Getters, PartialEq)]
+crate struct IntrospectionRequest {
+    #[get = "pub"]
+    token: String,
+    // Accepted for compliance, the token itself says what it is
+    #[get = "pub"]
+    token_type_hint: Option<String>,
+}
+
+/// Token introspection response (RFC 7662).  Inactive tokens only carry `active`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct IntrospectionResponse {
+    #[set = "pub"]
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    aid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    token_type: Option<TokenType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    rol: Option<Vec<Role>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    tfa: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    act: Option<Actor>,
+}
+
+/// The party acting on behalf of the token's subject (RFC 8693 section 4.1)
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct Actor {
+    // The user id of the administrator
+    #[get = "pub"]
+    sub: String,
+}
+
+impl Actor {
+    crate fn new(sub: String) -> Self {
+        Self { sub }
+    }
+}
+
+/// The kind of token a set of claims was issued as
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+crate enum TokenType {
+    Access,
+    Refresh,
+}
+
+impl Default for TokenType {
+    // Tokens minted before this claim existed were all refresh tokens.
+    fn default() -> Self {
+        Self::Refresh
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Getters, Serialize, Setters)]
 crate struct Claims {
+    #[get = "pub"]
     #[set = "pub"]
     iss: String,
     #[get = "pub"]
     #[set = "pub"]
     sub: String,
+    #[get = "pub"]
     iat: i64,
+    #[get = "pub"]
     nbf: i64,
+    #[get = "pub"]
     #[set = "pub"]
     exp: i64,
-    // Atlas User ID
+    #[serde(default)]
+    #[get = "pub"]
+    jti: String,
+    // Atlas User ID, empty for client credentials tokens
+    #[serde(default)]
+    #[get = "pub"]
     #[set = "pub"]
     aid: String,
+    // Service client the token was issued to by the client credentials grant
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[get = "pub"]
+    #[set = "pub"]
+    cid: String,
+    // Space delimited OAuth scopes
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[get = "pub"]
+    #[set = "pub"]
+    scope: String,
+    // Session the token was issued to
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[get = "pub"]
+    #[set = "pub"]
+    sid: String,
     // Is Two-Factor Authentication required?
+    #[get = "pub"]
     #[set = "pub"]
     tfa: bool,
-    // // Atlas User Roles
-    // #[get = "pub"]
-    // #[set = "pub"]
-    // rol: Vec<Role>,
+    // Access or Refresh token
+    #[serde(default)]
+    #[get = "pub"]
+    #[set = "pub"]
+    typ: TokenType,
+    // Atlas User Roles
+    #[serde(default)]
+    #[get = "pub"]
+    #[set = "pub"]
+    rol: Vec<Role>,
+    // Administrator impersonating the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[get = "pub"]
+    #[set = "pub"]
+    act: Option<Actor>,
 }
 
 impl Default for Claims {
@@ -170,9 +364,15 @@ impl Default for Claims {
             iat: now,
             nbf: now,
             exp,
+            jti: Uuid::new_v4().to_hyphenated().to_string(),
             aid: String::new(),
+            cid: String::new(),
+            scope: String::new(),
+            sid: String::new(),
             tfa: false,
-            // rol: Vec::new(),
+            typ: TokenType::Access,
+            rol: Vec::new(),
+            act: None,
         }
     }
 }
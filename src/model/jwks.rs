@@ -0,0 +1,52 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! JSON Web Key Models (RFC 7517)
+//!
+//! ```
+//! ```
+use getset::Setters;
+use serde_derive::{Deserialize, Serialize};
+
+/// A public signing key
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct Jwk {
+    #[set = "pub"]
+    kty: String,
+    #[set = "pub"]
+    kid: String,
+    #[serde(rename = "use")]
+    #[set = "pub"]
+    usage: String,
+    #[set = "pub"]
+    alg: String,
+    // RSA modulus and exponent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    e: Option<String>,
+    // EC curve and point
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    y: Option<String>,
+}
+
+/// The set of keys tokens may be signed with
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct Jwks {
+    #[set = "pub"]
+    keys: Vec<Jwk>,
+}
@@ -10,5 +10,13 @@
 //!
 //! ```
 //! ```
+crate mod api_key;
 crate mod auth;
+crate mod jwks;
+crate mod oauth;
+crate mod oidc;
+crate mod role;
+crate mod scope;
+crate mod session;
 crate mod system;
+crate mod user;
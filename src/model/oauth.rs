@@ -0,0 +1,132 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! OAuth 2.0 Models (RFC 6749, RFC 7636)
+//!
+//! ```
+//! ```
+use getset::{Getters, Setters};
+use rocket::FromForm;
+
+crate const RESPONSE_TYPE_CODE: &str = "code";
+crate const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+crate const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+crate const PKCE_S256: &str = "S256";
+
+// Error codes (RFC 6749 sections 4.1.2.1 and 5.2)
+crate const INVALID_REQUEST: &str = "invalid_request";
+crate const INVALID_CLIENT: &str = "invalid_client";
+crate const INVALID_GRANT: &str = "invalid_grant";
+crate const INVALID_SCOPE: &str = "invalid_scope";
+crate const UNAUTHORIZED_CLIENT: &str = "unauthorized_client";
+crate const UNSUPPORTED_GRANT_TYPE: &str = "unsupported_grant_type";
+crate const UNSUPPORTED_RESPONSE_TYPE: &str = "unsupported_response_type";
+
+/// Authorization request, sent as the query string of `/oauth/authorize`
+#[derive(Clone, Debug, Eq, FromForm, Getters, PartialEq)]
+crate struct AuthorizationRequest {
+    #[get = "pub"]
+    response_type: Option<String>,
+    #[get = "pub"]
+    client_id: String,
+    #[get = "pub"]
+    redirect_uri: String,
+    #[get = "pub"]
+    state: Option<String>,
+    #[get = "pub"]
+    code_challenge: Option<String>,
+    #[get = "pub"]
+    code_challenge_method: Option<String>,
+    #[get = "pub"]
+    scope: Option<String>,
+    // Echoed back in the ID token when `scope` includes `openid`
+    #[get = "pub"]
+    nonce: Option<String>,
+}
+
+/// The credentials the login page posts alongside an `AuthorizationRequest`
+#[derive(Clone, Debug, Eq, FromForm, Getters, PartialEq)]
+crate struct AuthorizationLogin {
+    #[get = "pub"]
+    username: String,
+    #[get = "pub"]
+    password: String,
+    // Required when the user has 2FA enabled
+    #[get = "pub"]
+    code: Option<String>,
+    // Must match the login nonce cookie set by `GET /oauth/authorize`
+    #[get = "pub"]
+    login_token: String,
+}
+
+/// An issued authorization code, stored by hash
+#[derive(Clone, Debug, Default, Eq, Getters, PartialEq, Setters)]
+crate struct AuthorizationCode {
+    #[get = "pub"]
+    #[set = "pub"]
+    code_hash: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    client_id: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    user_id: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    redirect_uri: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    code_challenge: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    scope: Option<String>,
+    #[get = "pub"]
+    #[set = "pub"]
+    nonce: Option<String>,
+    // When the user logged in, for the ID token's `auth_time`
+    #[get = "pub"]
+    #[set = "pub"]
+    auth_time: i64,
+    #[get = "pub"]
+    #[set = "pub"]
+    expires: i64,
+}
+
+/// Token endpoint request
+#[derive(Clone, Debug, Eq, FromForm, Getters, PartialEq)]
+crate struct TokenRequest {
+    #[get = "pub"]
+    grant_type: String,
+    #[get = "pub"]
+    code: Option<String>,
+    #[get = "pub"]
+    redirect_uri: Option<String>,
+    // Public clients identify themselves here, confidential clients use HTTP Basic
+    #[get = "pub"]
+    client_id: Option<String>,
+    #[get = "pub"]
+    code_verifier: Option<String>,
+    // A subset of the client's scopes, all of them if absent
+    #[get = "pub"]
+    scope: Option<String>,
+}
+
+/// The client and scopes a session or token was delegated to
+#[derive(Clone, Debug, Eq, Getters, PartialEq)]
+crate struct Grant {
+    #[get = "pub"]
+    client_id: String,
+    #[get = "pub"]
+    scope: String,
+}
+
+impl Grant {
+    crate fn new(client_id: String, scope: String) -> Self {
+        Self { client_id, scope }
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! OpenID Connect Models
+//!
+//! ```
+//! ```
+use crate::config;
+use getset::Setters;
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+
+crate const SCOPE_OPENID: &str = "openid";
+
+lazy_static! {
+    /// The externally visible base URL of the api, which OpenID Connect uses as the issuer
+    pub static ref PUBLIC_URL: String = {
+        let url: String = config::env_or("MOZIAS_PUBLIC_URL", "http://localhost:8000".to_string());
+        url.trim_end_matches('/').to_string()
+    };
+}
+
+/// ID token claims (OpenID Connect Core section 2)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct IdTokenClaims {
+    #[set = "pub"]
+    iss: String,
+    // The user id, which unlike the username never changes
+    #[set = "pub"]
+    sub: String,
+    // The client the token was issued to
+    #[set = "pub"]
+    aud: String,
+    #[set = "pub"]
+    exp: i64,
+    #[set = "pub"]
+    iat: i64,
+    #[set = "pub"]
+    auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[set = "pub"]
+    nonce: Option<String>,
+    #[set = "pub"]
+    name: String,
+    #[set = "pub"]
+    preferred_username: String,
+}
+
+/// Userinfo response (OpenID Connect Core section 5.3)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct UserInfo {
+    #[set = "pub"]
+    sub: String,
+    #[set = "pub"]
+    name: String,
+    #[set = "pub"]
+    preferred_username: String,
+}
+
+/// Provider metadata (OpenID Connect Discovery section 3)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct OpenIdConfiguration {
+    #[set = "pub"]
+    issuer: String,
+    #[set = "pub"]
+    authorization_endpoint: String,
+    #[set = "pub"]
+    token_endpoint: String,
+    #[set = "pub"]
+    userinfo_endpoint: String,
+    #[set = "pub"]
+    introspection_endpoint: String,
+    #[set = "pub"]
+    jwks_uri: String,
+    #[set = "pub"]
+    scopes_supported: Vec<String>,
+    #[set = "pub"]
+    response_types_supported: Vec<String>,
+    #[set = "pub"]
+    grant_types_supported: Vec<String>,
+    #[set = "pub"]
+    subject_types_supported: Vec<String>,
+    #[set = "pub"]
+    id_token_signing_alg_values_supported: Vec<String>,
+    #[set = "pub"]
+    token_endpoint_auth_methods_supported: Vec<String>,
+    #[set = "pub"]
+    code_challenge_methods_supported: Vec<String>,
+    #[set = "pub"]
+    claims_supported: Vec<String>,
+}
@@ -0,0 +1,43 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Role Models
+//!
+//! ```
+//! ```
+use getset::Getters;
+use serde_derive::{Deserialize, Serialize};
+
+/// A named role that can be granted to a user
+#[derive(Clone, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct Role {
+    #[get = "pub"]
+    id: String,
+    #[get = "pub"]
+    name: String,
+}
+
+impl Role {
+    crate fn new(id: String, name: String) -> Self {
+        Self { id, name }
+    }
+}
+
+/// A role a route can require via `RequireRole`
+crate trait RoleName {
+    /// The name of the role in `mozias_role`
+    const NAME: &'static str;
+}
+
+/// The administrator role
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct Admin;
+
+impl RoleName for Admin {
+    const NAME: &'static str = "admin";
+}
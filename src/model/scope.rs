@@ -0,0 +1,78 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Scope Models
+//!
+//! ```
+//! ```
+use crate::model::oidc::SCOPE_OPENID;
+
+/// A scope a route can require via `RequireScope`
+crate trait ScopeName {
+    /// The name of the scope in `mozias_client_scope` and the `scope` claim
+    const NAME: &'static str;
+}
+
+/// Read the caller's OpenID Connect profile
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct OpenId;
+
+impl ScopeName for OpenId {
+    const NAME: &'static str = SCOPE_OPENID;
+}
+
+/// List the user's sessions
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct SessionsRead;
+
+impl ScopeName for SessionsRead {
+    const NAME: &'static str = "sessions:read";
+}
+
+/// Revoke the user's sessions
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct SessionsWrite;
+
+impl ScopeName for SessionsWrite {
+    const NAME: &'static str = "sessions:write";
+}
+
+/// List the user's API keys
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct ApiKeysRead;
+
+impl ScopeName for ApiKeysRead {
+    const NAME: &'static str = "api_keys:read";
+}
+
+/// Revoke the user's API keys
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+crate struct ApiKeysWrite;
+
+impl ScopeName for ApiKeysWrite {
+    const NAME: &'static str = "api_keys:write";
+}
+
+/// Every scope a route can require
+crate const SCOPES: [&str; 5] = [
+    OpenId::NAME,
+    SessionsRead::NAME,
+    SessionsWrite::NAME,
+    ApiKeysRead::NAME,
+    ApiKeysWrite::NAME,
+];
+
+/// Is the given scope one a route can require?
+crate fn is_known(scope: &str) -> bool {
+    SCOPES.iter().any(|known| *known == scope)
+}
+
+/// Split a space-delimited `scope` value (RFC 6749 section 3.3)
+crate fn parse(scope: &str) -> Vec<String> {
+    scope.split_whitespace().map(str::to_string).collect()
+}
@@ -0,0 +1,97 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Session Models
+//!
+//! ```
+//! ```
+use chrono::NaiveDateTime;
+use getset::{Getters, Setters};
+use serde_derive::{Deserialize, Serialize};
+
+/// A logged in device
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize, Setters)]
+crate struct Session {
+    #[get = "pub"]
+    id: String,
+    #[get = "pub"]
+    user_agent: String,
+    #[get = "pub"]
+    ip: Option<String>,
+    // The client the session was granted to, if it isn't one of the user's own logins
+    #[get = "pub"]
+    client_id: Option<String>,
+    #[get = "pub"]
+    created_date: NaiveDateTime,
+    #[get = "pub"]
+    last_used_date: NaiveDateTime,
+    // Is this the session the request was made from?
+    #[set = "pub"]
+    current: bool,
+}
+
+impl Session {
+    crate fn new(
+        id: String,
+        user_agent: String,
+        ip: Option<String>,
+        client_id: Option<String>,
+        created_date: NaiveDateTime,
+        last_used_date: NaiveDateTime,
+    ) -> Self {
+        Self {
+            id,
+            user_agent,
+            ip,
+            client_id,
+            created_date,
+            last_used_date,
+            current: false,
+        }
+    }
+}
+
+/// Browser session login
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct SessionLogin {
+    #[get = "pub"]
+    username: String,
+    #[get = "pub"]
+    password: String,
+    // Required when the user has 2FA enabled
+    #[serde(default)]
+    #[get = "pub"]
+    code: Option<String>,
+}
+
+/// The CSRF token pages must echo in `X-CSRF-Token` on unsafe requests
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct SessionLoginResponse {
+    #[set = "pub"]
+    csrf_token: String,
+}
+
+/// What the encrypted session cookie holds
+#[derive(Clone, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize, Setters)]
+crate struct SessionCookie {
+    #[get = "pub"]
+    #[set = "pub"]
+    sid: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    aid: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    sub: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    csrf: String,
+    #[get = "pub"]
+    #[set = "pub"]
+    exp: i64,
+}
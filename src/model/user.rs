@@ -0,0 +1,62 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! User Models
+//!
+//! ```
+//! ```
+use getset::{Getters, Setters};
+use serde_derive::{Deserialize, Serialize};
+
+/// Self-registration request
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct Registration {
+    #[get = "pub"]
+    username: String,
+    #[get = "pub"]
+    password: String,
+    #[get = "pub"]
+    name: String,
+}
+
+/// Password change request
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct PasswordChange {
+    #[get = "pub"]
+    current_password: String,
+    #[get = "pub"]
+    new_password: String,
+}
+
+/// Forgotten password request
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct PasswordResetRequest {
+    #[get = "pub"]
+    username: String,
+}
+
+/// Password reset confirmation
+#[derive(Clone, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+crate struct PasswordResetConfirm {
+    /// The token handed to the notifier
+    #[get = "pub"]
+    token: String,
+    #[get = "pub"]
+    new_password: String,
+}
+
+/// The public view of a user
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, Setters)]
+crate struct UserResponse {
+    #[set = "pub"]
+    id: String,
+    #[set = "pub"]
+    username: String,
+    #[set = "pub"]
+    name: String,
+}
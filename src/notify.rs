@@ -0,0 +1,57 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! User Notifications
+//!
+//! ```
+//! ```
+use crate::error::MoziasApiResult;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The notifier managed by rocket
+crate type SharedNotifier = Arc<dyn Notifier + Send + Sync>;
+
+/// Delivers out-of-band messages to users
+crate trait Notifier {
+    /// Hand a password reset token to the given user
+    fn password_reset(&self, username: &str, token: &str) -> MoziasApiResult<()>;
+}
+
+/// Writes notifications to the file named by `MOZIAS_NOTIFY_FILE`, or stdout if unset.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+crate struct FileNotifier {
+    path: Option<PathBuf>,
+}
+
+impl FileNotifier {
+    crate fn from_env() -> Self {
+        Self {
+            path: env::var("MOZIAS_NOTIFY_FILE").ok().map(PathBuf::from),
+        }
+    }
+
+    fn write(&self, message: &str) -> MoziasApiResult<()> {
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", message)?;
+        } else {
+            println!("{}", message);
+        }
+        Ok(())
+    }
+}
+
+impl Notifier for FileNotifier {
+    fn password_reset(&self, username: &str, token: &str) -> MoziasApiResult<()> {
+        self.write(&format!("password reset for {}: {}", username, token))
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Password Hashing
+//!
+//! ```
+//! ```
+use crate::config::env_or;
+use crate::error::MoziasApiResult;
+use argon2::{Config, Variant, Version};
+use lazy_static::lazy_static;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::env;
+
+const SALT_LEN: usize = 16;
+// The dummy hash never matches anything, so it doesn't need the real key, and
+// not needing it lets it be created before ARGON2_SECRET_KEY is read
+const DUMMY_SECRET_KEY: &[u8] = b"mozias-api dummy secret key";
+
+lazy_static! {
+    static ref PARAMS: Params = Params::from_env();
+    // Verified against when a login names an unknown user, so the response
+    // takes as long as it would for a real one.
+    static ref DUMMY_HASH: Result<String, String> =
+        hash_with("mozias-api dummy password", DUMMY_SECRET_KEY).map_err(|e| e.to_string());
+}
+
+/// The Argon2 cost parameters new hashes are created with
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Params {
+    variant: Variant,
+    mem_cost: u32,
+    time_cost: u32,
+    lanes: u32,
+}
+
+impl Params {
+    fn from_env() -> Self {
+        let defaults = Config::default();
+        Self {
+            variant: env::var("MOZIAS_ARGON2_VARIANT")
+                .ok()
+                .and_then(|variant| Variant::from_str(&variant).ok())
+                .unwrap_or(defaults.variant),
+            mem_cost: env_or("MOZIAS_ARGON2_MEMORY", defaults.mem_cost),
+            time_cost: env_or("MOZIAS_ARGON2_ITERATIONS", defaults.time_cost),
+            lanes: env_or("MOZIAS_ARGON2_LANES", defaults.lanes),
+        }
+    }
+
+    fn config<'a>(&self, secret: &'a [u8]) -> Config<'a> {
+        let mut config = Config::default();
+        config.secret = secret;
+        config.variant = self.variant;
+        config.mem_cost = self.mem_cost;
+        config.time_cost = self.time_cost;
+        config.lanes = self.lanes;
+        config
+    }
+
+    /// Was the given encoded hash, e.g. `$argon2i$v=19$m=4096,t=3,p=1$<salt>$<hash>`,
+    /// created with these parameters?
+    fn matches(&self, encoded: &str) -> bool {
+        let parts: Vec<&str> = encoded.split('$').collect();
+
+        parts.len() == 6
+            && parts[1] == self.variant.as_lowercase_str()
+            && parts[2] == format!("v={}", Version::default().as_u32())
+            && parts[3] == format!("m={},t={},p={}", self.mem_cost, self.time_cost, self.lanes)
+    }
+}
+
+/// Hash the given password with Argon2, keyed with `ARGON2_SECRET_KEY`.
+crate fn hash(password: &str) -> MoziasApiResult<String> {
+    let secret_key = env::var("ARGON2_SECRET_KEY")?;
+    hash_with(password, secret_key.as_bytes())
+}
+
+fn hash_with(password: &str, secret_key: &[u8]) -> MoziasApiResult<String> {
+    let mut salt = [0_u8; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| "unable to generate salt")?;
+
+    let config = PARAMS.config(secret_key);
+    Ok(argon2::hash_encoded(password.as_bytes(), &salt, &config)?)
+}
+
+/// Verify the given password against an encoded Argon2 hash, keyed with `ARGON2_SECRET_KEY`.
+crate fn verify(encoded: &str, password: &str) -> MoziasApiResult<bool> {
+    let secret_key = env::var("ARGON2_SECRET_KEY")?;
+    verify_with(encoded, password, secret_key.as_bytes())
+}
+
+fn verify_with(encoded: &str, password: &str, secret_key: &[u8]) -> MoziasApiResult<bool> {
+    Ok(argon2::verify_encoded_ext(
+        encoded,
+        password.as_bytes(),
+        secret_key,
+        &[],
+    )?)
+}
+
+/// Was the given encoded hash created with outdated Argon2 parameters?
+crate fn needs_rehash(encoded: &str) -> bool {
+    !PARAMS.matches(encoded)
+}
+
+/// Compute the dummy hash up front so the first unknown-user login isn't slower
+/// than the rest, and so a dummy hash that can't be created fails at startup.
+crate fn init_dummy_hash() -> MoziasApiResult<()> {
+    dummy_hash().map(|_| ())
+}
+
+fn dummy_hash() -> MoziasApiResult<&'static str> {
+    match &(*DUMMY_HASH) {
+        Ok(dummy_hash) => Ok(dummy_hash),
+        Err(e) => Err(format!("unable to create dummy password hash: {}", e).into()),
+    }
+}
+
+/// Burn the same Argon2 work as `verify` without a real hash to check against.
+crate fn verify_dummy(password: &str) -> MoziasApiResult<()> {
+    let _ = verify(dummy_hash()?, password)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dummy_hash, hash_with, verify_with, Params};
+    use argon2::Variant;
+    use std::time::{Duration, Instant};
+
+    const ROUNDS: u32 = 3;
+    const SECRET_KEY: &[u8] = b"mozias-api test secret key";
+    const PARAMS: Params = Params {
+        variant: Variant::Argon2i,
+        mem_cost: 4096,
+        time_cost: 3,
+        lanes: 1,
+    };
+    const SALT_AND_HASH: &str = "c29tZXNhbHQ$iWh06vD8Fy27wf9npn6FXWiCX4K6pW6Ue1Bnzz07Z8A";
+
+    fn timed<F: Fn()>(f: F) -> Duration {
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            f();
+        }
+        start.elapsed()
+    }
+
+    #[test]
+    fn dummy_hash_uses_current_params() {
+        let dummy_hash = dummy_hash().expect("dummy hash");
+        assert!(super::PARAMS.matches(dummy_hash));
+    }
+
+    #[test]
+    fn dummy_verify_is_not_much_cheaper() {
+        let encoded = hash_with("correct horse", SECRET_KEY).expect("hashed password");
+        let dummy_hash = dummy_hash().expect("dummy hash");
+
+        let real = timed(|| {
+            assert!(!verify_with(&encoded, "battery staple", SECRET_KEY).expect("verified"));
+        });
+        let dummy = timed(|| {
+            assert!(!verify_with(dummy_hash, "battery staple", SECRET_KEY).expect("verified"));
+        });
+
+        // Only a sanity check that the dummy does real Argon2 work; the params
+        // test above is what pins the cost
+        assert!(
+            dummy * 10 > real,
+            "verify took {:?}, verify_dummy took {:?}",
+            real,
+            dummy
+        );
+    }
+
+    #[test]
+    fn matches_same_params() {
+        assert!(PARAMS.matches(&format!("$argon2i$v=19$m=4096,t=3,p=1${}", SALT_AND_HASH)));
+    }
+
+    #[test]
+    fn rejects_other_params() {
+        let others = [
+            "$argon2id$v=19$m=4096,t=3,p=1$",
+            "$argon2i$v=16$m=4096,t=3,p=1$",
+            "$argon2i$v=19$m=8192,t=3,p=1$",
+            "$argon2i$v=19$m=4096,t=4,p=1$",
+            "$argon2i$v=19$m=4096,t=3,p=2$",
+            "$argon2i$v=19$t=3,m=4096,p=1$",
+        ];
+
+        for other in &others {
+            let encoded = format!("{}{}", other, SALT_AND_HASH);
+            assert!(!PARAMS.matches(&encoded), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        assert!(!PARAMS.matches(""));
+        assert!(!PARAMS.matches("$argon2i$v=19$m=4096,t=3,p=1"));
+        assert!(!PARAMS.matches(&format!("$argon2i$v=19$m=4096,t=3,p=1${}$", SALT_AND_HASH)));
+        assert!(!PARAMS.matches(&format!("argon2i$v=19$m=4096,t=3,p=1${}", SALT_AND_HASH)));
+    }
+}
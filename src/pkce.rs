@@ -0,0 +1,76 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Proof Key for Code Exchange (RFC 7636)
+//!
+//! Only the `S256` method is supported.
+//!
+//! ```
+//! ```
+use ring::constant_time::verify_slices_are_equal;
+use ring::digest::{self, SHA256};
+use std::ops::RangeInclusive;
+
+const VERIFIER_LEN: RangeInclusive<usize> = 43..=128;
+// The base64url encoding of a SHA-256 digest
+const CHALLENGE_LEN: usize = 43;
+
+/// Is the given code challenge shaped like an `S256` challenge?
+crate fn is_valid_challenge(challenge: &str) -> bool {
+    challenge.len() == CHALLENGE_LEN && challenge.chars().all(is_unreserved)
+}
+
+/// Check the given code verifier against the challenge sent with the authorization request.
+crate fn verify(challenge: &str, verifier: &str) -> bool {
+    if !VERIFIER_LEN.contains(&verifier.len()) || !verifier.chars().all(is_unreserved) {
+        return false;
+    }
+
+    let digest = digest::digest(&SHA256, verifier.as_bytes());
+    let expected = base64::encode_config(digest.as_ref(), base64::URL_SAFE_NO_PAD);
+    verify_slices_are_equal(expected.as_bytes(), challenge.as_bytes()).is_ok()
+}
+
+fn is_unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_' || c == '~'
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_valid_challenge, verify};
+
+    // RFC 7636 appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mJ92K5BhZXKDNSn7q6Gp9hKgTRXxgk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn verifies_rfc_7636_example() {
+        assert!(is_valid_challenge(CHALLENGE));
+        assert!(verify(CHALLENGE, VERIFIER));
+    }
+
+    #[test]
+    fn rejects_wrong_verifier() {
+        let mut wrong = VERIFIER.to_string();
+        let _ = wrong.pop();
+        wrong.push('h');
+        assert!(!verify(CHALLENGE, &wrong));
+        assert!(!verify(CHALLENGE, CHALLENGE));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        // Too short, too long, and outside the unreserved characters
+        assert!(!verify(CHALLENGE, &VERIFIER[..42]));
+        assert!(!verify(CHALLENGE, &"a".repeat(129)));
+        assert!(!verify(CHALLENGE, &format!("{}+", &VERIFIER[..42])));
+        assert!(!is_valid_challenge(&CHALLENGE[..42]));
+        assert!(!is_valid_challenge(&format!("{}=", &CHALLENGE[..42])));
+        assert!(!is_valid_challenge(""));
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! API Key Routes
+//!
+//! ```
+//! ```
+use crate::db::api_key as db;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::auth::{OwnAccount, RequireScope};
+use crate::model::api_key::{ApiKey, ApiKeyRequest};
+use crate::model::scope::{self, ApiKeysRead, ApiKeysWrite};
+use crate::secret;
+use chrono::{NaiveDateTime, Utc};
+use mysql::Pool;
+use rocket::response::status::Created;
+use rocket::{delete, get, post, State};
+use rocket_contrib::json::Json;
+use uuid::Uuid;
+
+// Lets secret scanners recognize leaked keys
+const API_KEY_PREFIX: &str = "mozias_";
+
+#[post("/users/me/api-keys", data = "<request>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn create(
+    pool: State<'_, Pool>,
+    user: OwnAccount,
+    request: Json<ApiKeyRequest>,
+) -> MoziasApiResult<Created<Json<ApiKey>>> {
+    // An impersonator minting a key would outlive the impersonation
+    let user = user.user();
+    let name = request.name().trim();
+    let now = NaiveDateTime::from_timestamp(Utc::now().timestamp(), 0);
+
+    // A key without scopes would authenticate yet be refused by every route
+    if name.is_empty()
+        || request.scopes().is_empty()
+        || request
+            .scopes()
+            .iter()
+            .any(|requested| !scope::is_known(requested))
+        || request.expires().map_or(false, |expires| expires <= now)
+    {
+        return Err(MoziasApiErrKind::BadRequest.into());
+    }
+
+    let id = Uuid::new_v4().to_hyphenated().to_string();
+    let key = format!("{}{}", API_KEY_PREFIX, secret::generate()?);
+    let mut api_key = ApiKey::new(
+        id.clone(),
+        name.to_string(),
+        request.scopes().clone(),
+        *request.expires(),
+        None,
+        now,
+    );
+
+    db::insert_api_key(&*pool, user.aid(), &api_key, &secret::hash(&key))?;
+
+    // The only time the raw key leaves the server
+    let _ = api_key.set_key(Some(key));
+    Ok(Created(
+        format!("/api/v1/users/me/api-keys/{}", id),
+        Some(Json(api_key)),
+    ))
+}
+
+#[get("/users/me/api-keys")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn list(
+    pool: State<'_, Pool>,
+    user: RequireScope<ApiKeysRead>,
+) -> MoziasApiResult<Json<Vec<ApiKey>>> {
+    Ok(Json(db::api_keys_by_user_id(&*pool, user.user().aid())?))
+}
+
+#[delete("/users/me/api-keys/<id>")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn revoke(
+    pool: State<'_, Pool>,
+    user: RequireScope<ApiKeysWrite>,
+    id: String,
+) -> MoziasApiResult<()> {
+    if db::delete_api_key(&*pool, &id, user.user().aid())? {
+        Ok(())
+    } else {
+        Err(MoziasApiErrKind::NotFound.into())
+    }
+}
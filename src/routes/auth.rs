@@ -10,64 +10,475 @@
 //!
 //! ```
 //! ```
+use crate::config;
 use crate::db::auth as db;
+use crate::db::{client, in_txn, lockout, refresh, role, session, user};
+use crate::denylist::SharedDenylist;
 use crate::error::{MoziasApiErrKind, MoziasApiResult};
-use crate::model::auth::{Claims, Credentials, TokenResponse, ISSUER, SECONDS_PER_YEAR};
+use crate::guards::auth::{OwnAccount, RequireRole};
+use crate::guards::client::{AuthenticatedClient, ClientInfo};
+use crate::lockout::Lockout;
+use crate::model::auth::{
+    AccessTokenResponse, Actor, Claims, Credentials, IntrospectionRequest, IntrospectionResponse,
+    RefreshRequest, TokenResponse, TokenRevocation, TokenType, ISSUER, SECONDS_PER_MINUTE,
+    SECONDS_PER_YEAR,
+};
+use crate::model::oauth::Grant;
+use crate::model::role::Admin;
+use crate::{password, secret, token};
 use chrono::Utc;
-use jsonwebtoken::{Algorithm, Header};
+use mysql::prelude::GenericConnection;
 use mysql::Pool;
-use rocket::{post, State};
+use rocket::request::Form;
+use rocket::{delete, post, State};
 use rocket_contrib::json::Json;
-use std::env;
+use uuid::Uuid;
+
+const DEFAULT_IMPERSONATION_SECONDS: i64 = SECONDS_PER_MINUTE * 15;
 
 #[post("/auth/token", data = "<auth>", format = "application/json")]
 #[allow(clippy::needless_pass_by_value)]
 crate fn auth(
     pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    client: ClientInfo,
     auth: Json<Credentials>,
 ) -> MoziasApiResult<Json<TokenResponse>> {
     let username = auth.username();
-    let given_password = auth.password();
-    let user_vec = db::auth_info_by_username(&*pool, &username)?;
+    let (id, tfa_enabled) = check_password(&*pool, &*lockout, &client, username, auth.password())?;
+    let mut token_response = TokenResponse::default();
+
+    if tfa_enabled {
+        // hand back a limited token that must be exchanged with a valid TOTP code
+        let mut claims = Claims::default();
+        let _ = claims.set_iss(ISSUER.to_string());
+        let _ = claims.set_sub(username.clone());
+        let _ = claims.set_aid(id);
+        let _ = claims.set_tfa(true);
+        let _ = token_response.set_tfa_token(Some(token::encode(&claims)?));
+    } else {
+        let (_, refresh_tok) = refresh_token(&*pool, &id, username, &client, None)?;
+        let _ = token_response.set_refresh_token(Some(refresh_tok));
+    }
+    Ok(Json(token_response))
+}
+
+/// Check the given username and password, subject to lockout, returning the
+/// user id and whether the user has 2FA enabled.
+crate fn check_password(
+    pool: &Pool,
+    lockout: &Lockout,
+    client: &ClientInfo,
+    username: &str,
+    given_password: &str,
+) -> MoziasApiResult<(String, bool)> {
+    Lockout::check(pool, username, client.ip_str())?;
+
+    let user_vec = db::auth_info_by_username(pool, username)?;
 
     if user_vec.len() == 1 {
-        let secret_key = env::var("ARGON2_SECRET_KEY")?;
-        let secret_bytes = secret_key.as_bytes();
         let id = &user_vec[0].0;
-        let profile_id = &user_vec[0].1;
-        let password = &user_vec[0].2;
-        let refresh_tok_opt = &user_vec[0].3;
-
-        if argon2::verify_encoded_ext(password, given_password.as_bytes(), secret_bytes, &[])? {
-            let mut token_response = TokenResponse::default();
-            let _ = token_response.set_refresh_token(if let Some(refresh_tok) = refresh_tok_opt {
-                refresh_tok.clone()
-            } else {
-                // create a new refresh token and store it
-                let now = Utc::now().timestamp();
-                let mut claims = Claims::default();
-                let _ = claims.set_iss(ISSUER.to_string());
-                let _ = claims.set_sub(username.clone());
-                let _ = claims.set_aid(id.clone());
-                let _ = claims.set_tfa(false);
-                let _ = claims.set_exp(now + SECONDS_PER_YEAR);
-                // if let Ok(roles) = role::find_roles_by_user_id(&pool, id) {
-                //     claims.set_rol(roles);
-                // }
-
-                let mut header = Header::default();
-                header.alg = Algorithm::HS512;
-                let secret = env::var("JWT_SECRET")?;
-                let token = jsonwebtoken::encode(&header, &claims, secret.as_bytes())?;
-
-                db::add_refresh_token_to_profile(&*pool, &profile_id, &token)?;
-                token
-            });
-            Ok(Json(token_response))
+        let hash = &user_vec[0].1;
+        let disabled = user_vec[0].2;
+        let tfa_enabled = user_vec[0].3;
+
+        if password::verify(hash, given_password)? && !disabled {
+            Lockout::record_success(pool, username)?;
+
+            // The plaintext is only available now, so migrate old hashes while we have it
+            if password::needs_rehash(hash) {
+                let new_hash = password::hash(given_password)?;
+
+                // Losing a race with a password change just leaves the new password alone
+                if let Err(e) = user::rehash_password(pool, id, hash, &new_hash) {
+                    eprintln!("{}", e);
+                }
+            }
+
+            Ok((id.clone(), tfa_enabled))
         } else {
+            lockout.record_failure(username, client.ip_str())?;
             Err(MoziasApiErrKind::Unauthorized.into())
         }
+    } else {
+        // Same work and same response as a bad password, so timing doesn't
+        // reveal which usernames exist
+        password::verify_dummy(given_password)?;
+        lockout.record_failure(username, client.ip_str())?;
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
+
+/// Create a new session for a login and mint its first refresh token.  Every
+/// login gets its own session, which is also its rotation family, so no two
+/// devices share a credential and each can be revoked on its own.  Sessions
+/// granted to a client are limited to the grant's scopes.
+crate fn refresh_token(
+    pool: &Pool,
+    id: &str,
+    username: &str,
+    client: &ClientInfo,
+    grant: Option<&Grant>,
+) -> MoziasApiResult<(String, String)> {
+    let session_id = Uuid::new_v4().to_hyphenated().to_string();
+    in_txn(|txn| {
+        session::insert_session(
+            txn,
+            &session_id,
+            id,
+            client.user_agent(),
+            client.ip_str(),
+            grant,
+        )?;
+        mint_refresh_token(pool, txn, id, username, &session_id, client, grant)
+    })
+    .map(|refresh_tok| (session_id, refresh_tok))
+}
+
+/// Create a new session for a browser login, which the session cookie stands
+/// in for instead of a refresh token.
+crate fn device_session(id: &str, client: &ClientInfo) -> MoziasApiResult<String> {
+    let session_id = Uuid::new_v4().to_hyphenated().to_string();
+    in_txn(|txn| {
+        session::insert_session(
+            txn,
+            &session_id,
+            id,
+            client.user_agent(),
+            client.ip_str(),
+            None,
+        )
+    })?;
+    Ok(session_id)
+}
+
+/// Create a new refresh token for the given session, record it, and store it on the session.
+fn mint_refresh_token<T>(
+    pool: &Pool,
+    conn: &mut T,
+    id: &str,
+    username: &str,
+    session_id: &str,
+    client: &ClientInfo,
+    grant: Option<&Grant>,
+) -> MoziasApiResult<String>
+where
+    T: GenericConnection,
+{
+    let now = Utc::now().timestamp();
+    let mut claims = Claims::default();
+    let _ = claims.set_iss(ISSUER.to_string());
+    let _ = claims.set_sub(username.to_string());
+    let _ = claims.set_aid(id.to_string());
+    let _ = claims.set_sid(session_id.to_string());
+    let _ = claims.set_tfa(false);
+    let _ = claims.set_typ(TokenType::Refresh);
+    let _ = claims.set_exp(now + SECONDS_PER_YEAR);
+    let _ = claims.set_rol(role::find_roles_by_user_id(pool, id)?);
+    set_grant(&mut claims, grant);
+
+    let token = token::encode(&claims)?;
+
+    refresh::insert_issued_refresh_token(conn, claims.jti(), session_id, id, *claims.exp())?;
+    // Stored hashed, so reading the table doesn't hand out live refresh tokens
+    let token_hash = secret::hash(&token);
+    session::update_session_refresh_token(conn, session_id, &token_hash, client.ip_str())?;
+    Ok(token)
+}
+
+/// Revoke every refresh token issued to the given user.
+crate fn revoke_refresh_tokens<T>(conn: &mut T, user_id: &str) -> MoziasApiResult<()>
+where
+    T: GenericConnection,
+{
+    refresh::revoke_user_refresh_tokens(conn, user_id)?;
+    session::delete_user_sessions(conn, user_id)
+}
+
+/// Revoke the given session of the given user, returning `false` if no such session exists.
+crate fn revoke_session(user_id: &str, session_id: &str) -> MoziasApiResult<bool> {
+    in_txn(|txn| {
+        if session::delete_session(txn, session_id, user_id)? {
+            refresh::revoke_refresh_token_family(txn, session_id)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    })
+}
+
+#[post("/auth/refresh", data = "<refresh>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn refresh(
+    pool: State<'_, Pool>,
+    denylist: State<'_, SharedDenylist>,
+    client: ClientInfo,
+    refresh: Json<RefreshRequest>,
+) -> MoziasApiResult<Json<AccessTokenResponse>> {
+    let refresh_token = refresh.refresh_token();
+    let refresh_claims =
+        token::decode(refresh_token).map_err(|_| MoziasApiErrKind::Unauthorized)?;
+
+    if *refresh_claims.typ() != TokenType::Refresh || denylist.is_denied(refresh_claims.jti()) {
+        return Err(MoziasApiErrKind::Unauthorized.into());
+    }
+
+    let jti = refresh_claims.jti();
+    let issued_vec = refresh::issued_refresh_token_by_jti(&*pool, jti)?;
+
+    if let Some((family_id, user_id, status)) = issued_vec.first() {
+        if status == refresh::ROTATED {
+            // A token that has already been rotated away is being replayed.  We
+            // can't tell which party is legitimate, so kill the whole family.
+            in_txn(|txn| {
+                refresh::revoke_refresh_token_family(txn, family_id)?;
+                refresh::insert_refresh_token_reuse(txn, jti, family_id, user_id)?;
+                session::delete_session(txn, family_id, user_id).map(|_| ())
+            })?;
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        } else if status != refresh::ACTIVE {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+    } else {
+        return Err(MoziasApiErrKind::Unauthorized.into());
+    }
+
+    let session_vec = session::session_by_refresh_token_hash(&*pool, &secret::hash(refresh_token))?;
+
+    if session_vec.len() == 1
+        && session_vec[0].1 == *refresh_claims.aid()
+        && !db::is_user_disabled(&*pool, &session_vec[0].1)?
+    {
+        let (session_id, id, username, client_id, scope) = &session_vec[0];
+        // Tokens refreshed for a client keep the scopes the client was granted
+        let grant = client_id
+            .as_ref()
+            .map(|client_id| Grant::new(client_id.clone(), scope.clone().unwrap_or_default()));
+
+        let rotated_token = in_txn(|txn| {
+            refresh::rotate_refresh_token(txn, jti)?;
+            mint_refresh_token(
+                &*pool,
+                txn,
+                id,
+                username,
+                session_id,
+                &client,
+                grant.as_ref(),
+            )
+        })?;
+
+        let mut access_token_response =
+            access_token(&*pool, id, username, session_id, grant.as_ref())?;
+        let _ = access_token_response.set_refresh_token(Some(rotated_token));
+        Ok(Json(access_token_response))
     } else {
         Err(MoziasApiErrKind::Unauthorized.into())
     }
 }
+
+/// Mint a short-lived access token for the given session, limited to the
+/// grant's scopes if the session was granted to a client.
+crate fn access_token(
+    pool: &Pool,
+    id: &str,
+    username: &str,
+    session_id: &str,
+    grant: Option<&Grant>,
+) -> MoziasApiResult<AccessTokenResponse> {
+    // Claims default to a short-lived access token
+    let mut claims = Claims::default();
+    let _ = claims.set_iss(ISSUER.to_string());
+    let _ = claims.set_sub(username.to_string());
+    let _ = claims.set_aid(id.to_string());
+    let _ = claims.set_sid(session_id.to_string());
+    let _ = claims.set_tfa(false);
+    let _ = claims.set_rol(role::find_roles_by_user_id(pool, id)?);
+    set_grant(&mut claims, grant);
+
+    let mut access_token_response = AccessTokenResponse::default();
+    let _ = access_token_response.set_access_token(token::encode(&claims)?);
+    let _ = access_token_response.set_expires_in(claims.exp() - Utc::now().timestamp());
+    let _ = access_token_response.set_scope(grant.map(|grant| grant.scope().clone()));
+    Ok(access_token_response)
+}
+
+/// Tokens with a `cid` may only be used for their `scope`, even an empty one.
+fn set_grant(claims: &mut Claims, grant: Option<&Grant>) {
+    if let Some(grant) = grant {
+        let _ = claims.set_cid(grant.client_id().clone());
+        let _ = claims.set_scope(grant.scope().clone());
+    }
+}
+
+#[post("/auth/logout")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn logout(user: OwnAccount) -> MoziasApiResult<()> {
+    let user = user.user();
+
+    if user.sid().is_empty() {
+        in_txn(|txn| revoke_refresh_tokens(txn, user.aid()))
+    } else {
+        revoke_session(user.aid(), user.sid()).map(|_| ())
+    }
+}
+
+#[delete("/auth/tokens/<user_id>")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn revoke(_admin: RequireRole<Admin>, user_id: String) -> MoziasApiResult<()> {
+    in_txn(|txn| revoke_refresh_tokens(txn, &user_id))
+}
+
+#[post("/auth/impersonate/<user_id>")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn impersonate(
+    pool: State<'_, Pool>,
+    admin: RequireRole<Admin>,
+    user_id: String,
+) -> MoziasApiResult<Json<AccessTokenResponse>> {
+    let admin = admin.user();
+
+    match db::username_by_user_id(&*pool, &user_id)?.first() {
+        Some((username, false)) => {
+            let seconds = config::env_or(
+                "MOZIAS_IMPERSONATION_SECONDS",
+                DEFAULT_IMPERSONATION_SECONDS,
+            );
+
+            // No session and no refresh token, so it can't outlive `exp`
+            let mut claims = Claims::default();
+            let _ = claims.set_iss(ISSUER.to_string());
+            let _ = claims.set_sub(username.clone());
+            let _ = claims.set_aid(user_id.clone());
+            let _ = claims.set_tfa(false);
+            let _ = claims.set_exp(Utc::now().timestamp() + seconds);
+            let _ = claims.set_rol(role::find_roles_by_user_id(&*pool, &user_id)?);
+            let _ = claims.set_act(Some(Actor::new(admin.aid().clone())));
+
+            let mut access_token_response = AccessTokenResponse::default();
+            let _ = access_token_response.set_access_token(token::encode(&claims)?);
+            let _ = access_token_response.set_expires_in(seconds);
+            Ok(Json(access_token_response))
+        }
+        _ => Err(MoziasApiErrKind::NotFound.into()),
+    }
+}
+
+#[post("/auth/denylist", data = "<revocation>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn deny(
+    pool: State<'_, Pool>,
+    denylist: State<'_, SharedDenylist>,
+    admin: RequireRole<Admin>,
+    revocation: Json<TokenRevocation>,
+) -> MoziasApiResult<()> {
+    // An expired token needs no revoking, and without a `jti` there is nothing to deny
+    let claims = token::decode(revocation.token()).map_err(|_| MoziasApiErrKind::BadRequest)?;
+
+    if claims.jti().is_empty() {
+        return Err(MoziasApiErrKind::BadRequest.into());
+    }
+
+    // Service tokens have no user, so record the client instead
+    let subject_id = if claims.aid().is_empty() {
+        claims.cid()
+    } else {
+        claims.aid()
+    };
+    denylist.deny(
+        &*pool,
+        claims.jti(),
+        subject_id,
+        *claims.exp(),
+        admin.user().aid(),
+    )
+}
+
+/// Clear the user's lockouts, and the lockout on `ip` if one is given, since a
+/// user locked out from their own address is still locked out by the IP row.
+#[delete("/auth/lockouts/<username>?<ip>")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn unlock(
+    pool: State<'_, Pool>,
+    _admin: RequireRole<Admin>,
+    username: String,
+    ip: Option<String>,
+) -> MoziasApiResult<()> {
+    let mut cleared = lockout::clear_failures(&*pool, lockout::USERNAME, &username)?;
+
+    for (id, _, _, _) in db::auth_info_by_username(&*pool, &username)? {
+        cleared |= lockout::clear_failures(&*pool, lockout::TFA, &id)?;
+    }
+
+    if let Some(ip) = ip {
+        cleared |= lockout::clear_failures(&*pool, lockout::IP, &ip)?;
+    }
+
+    if cleared {
+        Ok(())
+    } else {
+        Err(MoziasApiErrKind::NotFound.into())
+    }
+}
+
+#[post(
+    "/auth/introspect",
+    data = "<introspection>",
+    format = "application/x-www-form-urlencoded"
+)]
+#[allow(clippy::needless_pass_by_value)]
+crate fn introspect(
+    pool: State<'_, Pool>,
+    denylist: State<'_, SharedDenylist>,
+    _client: AuthenticatedClient,
+    introspection: Form<IntrospectionRequest>,
+) -> MoziasApiResult<Json<IntrospectionResponse>> {
+    let mut response = IntrospectionResponse::default();
+
+    if let Ok(claims) = token::decode(introspection.token()) {
+        if !denylist.is_denied(claims.jti()) && is_active(&*pool, &claims)? {
+            let _ = response.set_active(true);
+            let _ = response.set_sub(Some(claims.sub().clone()));
+            let _ = response.set_aid(Some(claims.aid().clone()).filter(|aid| !aid.is_empty()));
+            let _ =
+                response.set_client_id(Some(claims.cid().clone()).filter(|cid| !cid.is_empty()));
+            let _ =
+                response.set_scope(Some(claims.scope().clone()).filter(|scope| !scope.is_empty()));
+            let _ = response.set_exp(Some(*claims.exp()));
+            let _ = response.set_iat(Some(*claims.iat()));
+            let _ = response.set_iss(Some(claims.iss().clone()));
+            let _ = response.set_token_type(Some(*claims.typ()));
+            let _ = response.set_rol(Some(claims.rol().clone()));
+            let _ = response.set_tfa(Some(*claims.tfa()));
+            let _ = response.set_act(claims.act().clone());
+        }
+    }
+
+    Ok(Json(response))
+}
+
+/// Has the token behind the given (already verified) claims been revoked, or
+/// its user or client disabled, since it was issued?
+fn is_active(pool: &Pool, claims: &Claims) -> MoziasApiResult<bool> {
+    if claims.aid().is_empty() {
+        return client::is_service_client(pool, claims.cid());
+    }
+
+    if db::is_user_disabled(pool, claims.aid())? {
+        return Ok(false);
+    }
+
+    match claims.typ() {
+        TokenType::Refresh => {
+            let issued_vec = refresh::issued_refresh_token_by_jti(pool, claims.jti())?;
+            Ok(issued_vec
+                .first()
+                .map_or(false, |(_, _, status)| status == refresh::ACTIVE))
+        }
+        // Revoking a session is how access tokens are revoked
+        TokenType::Access if !claims.sid().is_empty() => {
+            session::session_exists(pool, claims.sid(), claims.aid())
+        }
+        TokenType::Access => Ok(true),
+    }
+}
@@ -10,5 +10,12 @@
 //!
 //! ```
 //! ```
+crate mod api_key;
 crate mod auth;
+crate mod oauth;
+crate mod reset;
+crate mod session;
 crate mod system;
+crate mod tfa;
+crate mod user;
+crate mod well_known;
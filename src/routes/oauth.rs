@@ -0,0 +1,577 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! OAuth 2.0 Routes
+//!
+//! The authorization code flow with PKCE.  `GET /oauth/authorize` validates the
+//! request and sends the browser to the login page with the same query string,
+//! the login page posts the user's credentials back to `/oauth/authorize`, and
+//! the client exchanges the resulting code at `/oauth/token`.
+//!
+//! The login page is also given a `login_token`, which it posts back as a form
+//! field and which must match a private cookie set alongside it, so another
+//! site can't log the browser in as someone else.  A failed login sends the
+//! browser back to the login page with `error` set.
+//!
+//! Clients may ask for any subset of their scopes in `scope`, and get all of
+//! them if they don't ask.  The tokens they are issued carry the client in `cid`
+//! and are limited to routes that require one of the granted scopes.
+//!
+//! ```
+//! ```
+use crate::config;
+use crate::db::auth as db;
+use crate::db::{client, in_txn, oauth, user};
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::client::{AuthenticatedClient, ClientInfo};
+use crate::guards::cookie::build_cookie;
+use crate::lockout::Lockout;
+use crate::model::auth::{AccessTokenResponse, Claims, ISSUER, SECONDS_PER_MINUTE};
+use crate::model::oauth::{
+    AuthorizationCode, AuthorizationLogin, AuthorizationRequest, Grant, TokenRequest,
+    GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, INVALID_CLIENT, INVALID_GRANT,
+    INVALID_REQUEST, INVALID_SCOPE, PKCE_S256, RESPONSE_TYPE_CODE, UNAUTHORIZED_CLIENT,
+    UNSUPPORTED_GRANT_TYPE, UNSUPPORTED_RESPONSE_TYPE,
+};
+use crate::model::oidc::{IdTokenClaims, PUBLIC_URL, SCOPE_OPENID};
+use crate::model::scope;
+use crate::routes::auth::{access_token, check_password, refresh_token};
+use crate::routes::tfa;
+use crate::{keys, pkce, secret, token};
+use chrono::Utc;
+use mysql::prelude::GenericConnection;
+use mysql::Pool;
+use ring::constant_time::verify_slices_are_equal;
+use rocket::http::uri::Uri;
+use rocket::http::{Cookie, Cookies};
+use rocket::request::{Form, LenientForm};
+use rocket::response::Redirect;
+use rocket::{get, post, State};
+use rocket_contrib::json::Json;
+
+const DEFAULT_LOGIN_PAGE: &str = "/login.html";
+const LOGIN_NONCE_COOKIE: &str = "mozias_login_nonce";
+// Errors shown by the login page, not sent to the client
+const LOGIN_FAILED: &str = "login_failed";
+const LOGIN_EXPIRED: &str = "login_expired";
+const LOGIN_LOCKED: &str = "login_locked";
+const AUTHORIZATION_CODE_SECONDS: i64 = SECONDS_PER_MINUTE * 5;
+const ID_TOKEN_SECONDS: i64 = SECONDS_PER_MINUTE * 5;
+
+#[get("/oauth/authorize?<authorization..>")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn authorize(
+    pool: State<'_, Pool>,
+    mut cookies: Cookies<'_>,
+    authorization: LenientForm<AuthorizationRequest>,
+) -> MoziasApiResult<Redirect> {
+    validate_redirect_uri(&*pool, &authorization)?;
+
+    if let Err(error) = validate_authorization(&authorization) {
+        return Ok(redirect_to_client(&authorization, &[("error", error)]));
+    }
+
+    if granted_scope(
+        &*pool,
+        authorization.client_id(),
+        authorization.scope().as_ref(),
+    )?
+    .is_none()
+    {
+        return Ok(redirect_to_client(
+            &authorization,
+            &[("error", INVALID_SCOPE)],
+        ));
+    }
+
+    redirect_to_login(&authorization, &mut cookies, None)
+}
+
+#[post(
+    "/oauth/authorize?<authorization..>",
+    data = "<login>",
+    format = "application/x-www-form-urlencoded"
+)]
+#[allow(clippy::needless_pass_by_value)]
+crate fn login(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    client: ClientInfo,
+    mut cookies: Cookies<'_>,
+    authorization: LenientForm<AuthorizationRequest>,
+    login: Form<AuthorizationLogin>,
+) -> MoziasApiResult<Redirect> {
+    validate_redirect_uri(&*pool, &authorization)?;
+
+    let code_challenge = match validate_authorization(&authorization) {
+        Ok(code_challenge) => code_challenge,
+        Err(error) => return Ok(redirect_to_client(&authorization, &[("error", error)])),
+    };
+
+    if granted_scope(
+        &*pool,
+        authorization.client_id(),
+        authorization.scope().as_ref(),
+    )?
+    .is_none()
+    {
+        return Ok(redirect_to_client(
+            &authorization,
+            &[("error", INVALID_SCOPE)],
+        ));
+    }
+
+    // Each login page gets one attempt, whether it succeeds or not
+    let nonce = cookies.get_private(LOGIN_NONCE_COOKIE);
+    cookies.remove_private(Cookie::named(LOGIN_NONCE_COOKIE));
+
+    let nonce_matches = nonce.map_or(false, |nonce| {
+        verify_slices_are_equal(nonce.value().as_bytes(), login.login_token().as_bytes()).is_ok()
+    });
+
+    if !nonce_matches {
+        return redirect_to_login(&authorization, &mut cookies, Some(LOGIN_EXPIRED));
+    }
+
+    let id = match authenticate(&*pool, &*lockout, &client, &login) {
+        Ok(id) => id,
+        Err(e) => match e.kind() {
+            MoziasApiErrKind::Unauthorized => {
+                return redirect_to_login(&authorization, &mut cookies, Some(LOGIN_FAILED));
+            }
+            MoziasApiErrKind::TooManyRequests(_) => {
+                return redirect_to_login(&authorization, &mut cookies, Some(LOGIN_LOCKED));
+            }
+            _ => return Err(e),
+        },
+    };
+
+    let code = secret::generate()?;
+    let now = Utc::now().timestamp();
+    let mut authorization_code = AuthorizationCode::default();
+    let _ = authorization_code.set_code_hash(secret::hash(&code));
+    let _ = authorization_code.set_client_id(authorization.client_id().clone());
+    let _ = authorization_code.set_user_id(id);
+    let _ = authorization_code.set_redirect_uri(authorization.redirect_uri().clone());
+    let _ = authorization_code.set_code_challenge(code_challenge.to_string());
+    let _ = authorization_code.set_scope(authorization.scope().clone());
+    let _ = authorization_code.set_nonce(authorization.nonce().clone());
+    let _ = authorization_code.set_auth_time(now);
+    let _ = authorization_code.set_expires(now + AUTHORIZATION_CODE_SECONDS);
+    oauth::insert_authorization_code(&*pool, &authorization_code)?;
+
+    Ok(redirect_to_client(
+        &authorization,
+        &[("code", code.as_str())],
+    ))
+}
+
+#[post(
+    "/oauth/token",
+    data = "<token_request>",
+    format = "application/x-www-form-urlencoded"
+)]
+#[allow(clippy::needless_pass_by_value)]
+crate fn grant(
+    pool: State<'_, Pool>,
+    client: ClientInfo,
+    authenticated: Option<AuthenticatedClient>,
+    token_request: Form<TokenRequest>,
+) -> MoziasApiResult<Json<AccessTokenResponse>> {
+    let client_id = identify_client(
+        &*pool,
+        authenticated.as_ref(),
+        token_request.client_id().as_ref(),
+    )?;
+
+    match &token_request.grant_type()[..] {
+        GRANT_AUTHORIZATION_CODE => {
+            authorization_code_grant(&*pool, &client, &client_id, &token_request).map(Json)
+        }
+        GRANT_CLIENT_CREDENTIALS if authenticated.is_some() => {
+            client_credentials_grant(&*pool, &client_id, token_request.scope().as_ref()).map(Json)
+        }
+        GRANT_CLIENT_CREDENTIALS => Err(MoziasApiErrKind::OAuth(INVALID_CLIENT).into()),
+        _ => Err(MoziasApiErrKind::OAuth(UNSUPPORTED_GRANT_TYPE).into()),
+    }
+}
+
+/// Redirecting anywhere but a registered URI would make us an open redirector,
+/// so a bad client or redirect URI is reported to the user agent instead.
+fn validate_redirect_uri(pool: &Pool, authorization: &AuthorizationRequest) -> MoziasApiResult<()> {
+    let redirect_uris = client::client_redirect_uris(pool, authorization.client_id())?;
+
+    if redirect_uris.contains(authorization.redirect_uri()) {
+        Ok(())
+    } else {
+        Err(MoziasApiErrKind::OAuth(INVALID_REQUEST).into())
+    }
+}
+
+/// Check the parts of the request that are reported back to the client, returning the code challenge.
+fn validate_authorization(authorization: &AuthorizationRequest) -> Result<&str, &'static str> {
+    if authorization.response_type().as_ref().map(String::as_str) != Some(RESPONSE_TYPE_CODE) {
+        return Err(UNSUPPORTED_RESPONSE_TYPE);
+    }
+
+    if authorization
+        .code_challenge_method()
+        .as_ref()
+        .map(String::as_str)
+        != Some(PKCE_S256)
+    {
+        return Err(INVALID_REQUEST);
+    }
+
+    match authorization.code_challenge() {
+        Some(code_challenge) if pkce::is_valid_challenge(code_challenge) => {
+            Ok(code_challenge.as_str())
+        }
+        _ => Err(INVALID_REQUEST),
+    }
+}
+
+/// Check the posted credentials, and the code if the user has 2FA enabled.
+fn authenticate(
+    pool: &Pool,
+    lockout: &Lockout,
+    client: &ClientInfo,
+    login: &AuthorizationLogin,
+) -> MoziasApiResult<String> {
+    let (id, tfa_enabled) =
+        check_password(pool, lockout, client, login.username(), login.password())?;
+
+    if tfa_enabled && !tfa::is_valid_code(pool, lockout, &id, login.code().as_ref())? {
+        return Err(MoziasApiErrKind::Unauthorized.into());
+    }
+
+    Ok(id)
+}
+
+/// Send the browser to the login page with a fresh login nonce, and `error` if
+/// the last attempt failed.
+fn redirect_to_login(
+    authorization: &AuthorizationRequest,
+    cookies: &mut Cookies<'_>,
+    error: Option<&str>,
+) -> MoziasApiResult<Redirect> {
+    let nonce = secret::generate()?;
+    cookies.add_private(build_cookie(LOGIN_NONCE_COOKIE, nonce.clone(), true));
+
+    let mut params = vec![("login_token", nonce.as_str())];
+
+    if let Some(error) = error {
+        params.push(("error", error));
+    }
+
+    let login_page: String = config::env_or("MOZIAS_LOGIN_PAGE", DEFAULT_LOGIN_PAGE.to_string());
+    Ok(Redirect::to(format!(
+        "{}?{}&{}",
+        login_page,
+        authorization_query(authorization),
+        encode_params(&params)
+    )))
+}
+
+/// The scopes to grant the client for the given request.  `openid` is only
+/// granted when there is an asymmetric key to sign ID tokens with, since
+/// clients can't verify a token signed with `JWT_SECRET`.
+fn granted_scope(
+    pool: &Pool,
+    client_id: &str,
+    requested: Option<&String>,
+) -> MoziasApiResult<Option<String>> {
+    let allowed = client::client_scopes(pool, client_id)?;
+    let openid = keys::key_set()?.signing_key().is_some();
+    Ok(select_scope(&allowed, requested, openid))
+}
+
+/// The requested scopes, or all of the allowed scopes if none were requested.
+/// `openid` may always be requested if `openid` is set, and never otherwise.
+/// `None` if any requested scope is not permitted.
+fn select_scope(allowed: &[String], requested: Option<&String>, openid: bool) -> Option<String> {
+    let permitted = |scope: &str| {
+        if scope == SCOPE_OPENID {
+            openid
+        } else {
+            allowed.iter().any(|allowed| allowed == scope)
+        }
+    };
+
+    match requested {
+        Some(requested) => {
+            let requested = scope::parse(requested);
+
+            if requested.iter().all(|scope| permitted(scope)) {
+                Some(requested.join(" "))
+            } else {
+                None
+            }
+        }
+        None => Some(
+            allowed
+                .iter()
+                .filter(|scope| permitted(scope))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    }
+}
+
+/// Rebuild the query string of a validated authorization request.
+fn authorization_query(authorization: &AuthorizationRequest) -> String {
+    let mut params = vec![
+        ("response_type", RESPONSE_TYPE_CODE),
+        ("client_id", authorization.client_id().as_str()),
+        ("redirect_uri", authorization.redirect_uri().as_str()),
+        ("code_challenge_method", PKCE_S256),
+    ];
+
+    if let Some(code_challenge) = authorization.code_challenge() {
+        params.push(("code_challenge", code_challenge.as_str()));
+    }
+
+    if let Some(scope) = authorization.scope() {
+        params.push(("scope", scope.as_str()));
+    }
+
+    if let Some(nonce) = authorization.nonce() {
+        params.push(("nonce", nonce.as_str()));
+    }
+
+    if let Some(state) = authorization.state() {
+        params.push(("state", state.as_str()));
+    }
+
+    encode_params(&params)
+}
+
+/// Send the user agent back to the client's redirect URI with the given parameters and `state`.
+fn redirect_to_client(authorization: &AuthorizationRequest, params: &[(&str, &str)]) -> Redirect {
+    let mut params = params.to_vec();
+
+    if let Some(state) = authorization.state() {
+        params.push(("state", state.as_str()));
+    }
+
+    let redirect_uri = authorization.redirect_uri();
+    let separator = if redirect_uri.contains('?') { '&' } else { '?' };
+    Redirect::to(format!(
+        "{}{}{}",
+        redirect_uri,
+        separator,
+        encode_params(&params)
+    ))
+}
+
+fn encode_params(params: &[(&str, &str)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, Uri::percent_encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Confidential clients authenticate with HTTP Basic, public clients only name
+/// themselves and rely on PKCE.
+fn identify_client(
+    pool: &Pool,
+    authenticated: Option<&AuthenticatedClient>,
+    client_id: Option<&String>,
+) -> MoziasApiResult<String> {
+    if let Some(authenticated) = authenticated {
+        if client_id.map_or(true, |client_id| client_id == authenticated.client_id()) {
+            return Ok(authenticated.client_id().clone());
+        }
+    } else if let Some(client_id) = client_id {
+        if let Some(None) = client::client_secret_hash_by_id(pool, client_id)?.first() {
+            return Ok(client_id.clone());
+        }
+    }
+
+    Err(MoziasApiErrKind::OAuth(INVALID_CLIENT).into())
+}
+
+fn authorization_code_grant(
+    pool: &Pool,
+    client: &ClientInfo,
+    client_id: &str,
+    token_request: &TokenRequest,
+) -> MoziasApiResult<AccessTokenResponse> {
+    let (code, code_verifier, redirect_uri) = match (
+        token_request.code(),
+        token_request.code_verifier(),
+        token_request.redirect_uri(),
+    ) {
+        (Some(code), Some(code_verifier), Some(redirect_uri)) => {
+            (code, code_verifier, redirect_uri)
+        }
+        _ => return Err(MoziasApiErrKind::OAuth(INVALID_REQUEST).into()),
+    };
+    let code_hash = secret::hash(code);
+    let authorization_code =
+        in_txn(|txn| redeem_code(txn, &code_hash, client_id, redirect_uri, code_verifier))?;
+    let user_id = authorization_code.user_id();
+    // The client's scopes may have changed since the code was issued
+    let scope = granted_scope(pool, client_id, authorization_code.scope().as_ref())?
+        .ok_or_else(|| MoziasApiErrKind::OAuth(INVALID_SCOPE))?;
+    let grant = Grant::new(client_id.to_string(), scope);
+
+    match db::username_by_user_id(pool, user_id)?.first() {
+        Some((username, false)) => {
+            let (session_id, refresh_tok) =
+                refresh_token(pool, user_id, username, client, Some(&grant))?;
+            let mut access_token_response =
+                access_token(pool, user_id, username, &session_id, Some(&grant))?;
+            let _ = access_token_response.set_refresh_token(Some(refresh_tok));
+
+            if has_scope(authorization_code.scope(), SCOPE_OPENID) {
+                let _ =
+                    access_token_response.set_id_token(Some(id_token(pool, &authorization_code)?));
+            }
+            Ok(access_token_response)
+        }
+        _ => Err(MoziasApiErrKind::OAuth(INVALID_GRANT).into()),
+    }
+}
+
+/// Mint an access token for a service client itself.  There is no user, so
+/// `aid` is left empty, and no refresh token since the client can always
+/// authenticate again.
+fn client_credentials_grant(
+    pool: &Pool,
+    client_id: &str,
+    requested: Option<&String>,
+) -> MoziasApiResult<AccessTokenResponse> {
+    if !client::is_service_client(pool, client_id)? {
+        return Err(MoziasApiErrKind::OAuth(UNAUTHORIZED_CLIENT).into());
+    }
+
+    let scope = granted_scope(pool, client_id, requested)?
+        .ok_or_else(|| MoziasApiErrKind::OAuth(INVALID_SCOPE))?;
+
+    let mut claims = Claims::default();
+    let _ = claims.set_iss(ISSUER.to_string());
+    let _ = claims.set_sub(client_id.to_string());
+    let _ = claims.set_cid(client_id.to_string());
+    let _ = claims.set_scope(scope.clone());
+
+    let mut access_token_response = AccessTokenResponse::default();
+    let _ = access_token_response.set_access_token(token::encode(&claims)?);
+    let _ = access_token_response.set_expires_in(claims.exp() - Utc::now().timestamp());
+    let _ = access_token_response.set_scope(Some(scope).filter(|scope| !scope.is_empty()));
+    Ok(access_token_response)
+}
+
+/// Check the presented code against what it was issued for and mark it used.
+fn redeem_code<T>(
+    conn: &mut T,
+    code_hash: &str,
+    client_id: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> MoziasApiResult<AuthorizationCode>
+where
+    T: GenericConnection,
+{
+    match oauth::authorization_code_by_hash(conn, code_hash)?.pop() {
+        Some(code)
+            if code.client_id() == client_id
+                && code.redirect_uri() == redirect_uri
+                && pkce::verify(code.code_challenge(), code_verifier) =>
+        {
+            // Codes are single use, even if two exchanges race
+            if oauth::use_authorization_code(conn, code_hash)? {
+                Ok(code)
+            } else {
+                Err(MoziasApiErrKind::OAuth(INVALID_GRANT).into())
+            }
+        }
+        _ => Err(MoziasApiErrKind::OAuth(INVALID_GRANT).into()),
+    }
+}
+
+fn has_scope(scope: &Option<String>, wanted: &str) -> bool {
+    scope
+        .as_ref()
+        .map_or(false, |scope| scope.split_whitespace().any(|s| s == wanted))
+}
+
+/// Mint an OpenID Connect ID token for the user the given code was issued to.
+fn id_token(pool: &Pool, authorization_code: &AuthorizationCode) -> MoziasApiResult<String> {
+    let names_vec = user::names_by_user_id(pool, authorization_code.user_id())?;
+    let (username, name) = names_vec
+        .first()
+        .ok_or_else(|| MoziasApiErrKind::OAuth(INVALID_GRANT))?;
+    let now = Utc::now().timestamp();
+
+    let mut claims = IdTokenClaims::default();
+    let _ = claims.set_iss(PUBLIC_URL.clone());
+    let _ = claims.set_sub(authorization_code.user_id().clone());
+    let _ = claims.set_aud(authorization_code.client_id().clone());
+    let _ = claims.set_iat(now);
+    let _ = claims.set_exp(now + ID_TOKEN_SECONDS);
+    let _ = claims.set_auth_time(*authorization_code.auth_time());
+    let _ = claims.set_nonce(authorization_code.nonce().clone());
+    let _ = claims.set_name(name.clone());
+    let _ = claims.set_preferred_username(username.clone());
+
+    // Never fall back to JWT_SECRET, which clients have no way to verify with
+    let key = keys::key_set()?
+        .signing_key()
+        .ok_or_else(|| MoziasApiErrKind::OAuth(INVALID_SCOPE))?;
+    token::encode_with_key(key, &claims)
+}
+
+#[cfg(test)]
+mod test {
+    use super::select_scope;
+
+    fn allowed() -> Vec<String> {
+        vec!["sessions:read".to_string(), "openid".to_string()]
+    }
+
+    #[test]
+    fn grants_requested_subset() {
+        let requested = "sessions:read".to_string();
+        assert_eq!(
+            select_scope(&allowed(), Some(&requested), true),
+            Some("sessions:read".to_string())
+        );
+    }
+
+    #[test]
+    fn refuses_scope_not_allowed() {
+        let requested = "sessions:read sessions:write".to_string();
+        assert_eq!(select_scope(&allowed(), Some(&requested), true), None);
+    }
+
+    #[test]
+    fn grants_all_allowed_when_none_requested() {
+        assert_eq!(
+            select_scope(&allowed(), None, true),
+            Some("sessions:read openid".to_string())
+        );
+        assert_eq!(select_scope(&[], None, true), Some(String::new()));
+    }
+
+    #[test]
+    fn openid_requires_signing_key() {
+        let requested = "openid sessions:read".to_string();
+        assert_eq!(
+            select_scope(&[], Some(&"openid".to_string()), true),
+            Some("openid".to_string())
+        );
+        assert_eq!(select_scope(&allowed(), Some(&requested), false), None);
+        assert_eq!(
+            select_scope(&allowed(), None, false),
+            Some("sessions:read".to_string())
+        );
+    }
+}
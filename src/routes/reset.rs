@@ -0,0 +1,97 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Password Reset Routes
+//!
+//! ```
+//! ```
+use crate::db::reset as db;
+use crate::db::{auth, in_txn, user};
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::client::ClientInfo;
+use crate::lockout::Lockout;
+use crate::model::auth::SECONDS_PER_HOUR;
+use crate::model::user::{PasswordResetConfirm, PasswordResetRequest};
+use crate::notify::SharedNotifier;
+use crate::routes::auth::revoke_refresh_tokens;
+use crate::{password, secret};
+use chrono::Utc;
+use mysql::Pool;
+use rocket::response::status::Accepted;
+use rocket::{post, State};
+use rocket_contrib::json::Json;
+use std::sync::Arc;
+use std::thread;
+use uuid::Uuid;
+
+#[post("/auth/password-reset", data = "<reset>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn request(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    notifier: State<'_, SharedNotifier>,
+    client: ClientInfo,
+    reset: Json<PasswordResetRequest>,
+) -> MoziasApiResult<Accepted<()>> {
+    let username = reset.username();
+    Lockout::check_reset(&*pool, username, client.ip_str())?;
+    lockout.record_reset(username, client.ip_str())?;
+
+    let user_vec = auth::auth_info_by_username(&*pool, username)?;
+
+    // Unknown and disabled users get the same response so usernames can't be probed
+    if user_vec.len() == 1 && !user_vec[0].2 {
+        let token = secret::generate()?;
+        let expires = Utc::now().timestamp() + SECONDS_PER_HOUR;
+
+        db::insert_password_reset(
+            &*pool,
+            &Uuid::new_v4().to_hyphenated().to_string(),
+            &user_vec[0].0,
+            &secret::hash(&token),
+            expires,
+        )?;
+
+        // Delivery happens off the request, so neither its time nor its failure
+        // tells the caller the username exists
+        let notifier = Arc::clone(&*notifier);
+        let username = username.clone();
+        let _ = thread::spawn(move || {
+            if let Err(e) = notifier.password_reset(&username, &token) {
+                eprintln!("{}", e);
+            }
+        });
+    }
+
+    Ok(Accepted(None))
+}
+
+#[post(
+    "/auth/password-reset/confirm",
+    data = "<confirm>",
+    format = "application/json"
+)]
+#[allow(clippy::needless_pass_by_value)]
+crate fn confirm(confirm: Json<PasswordResetConfirm>) -> MoziasApiResult<()> {
+    if confirm.new_password().is_empty() {
+        return Err(MoziasApiErrKind::BadRequest.into());
+    }
+
+    let new_hash = password::hash(confirm.new_password())?;
+    let token_hash = secret::hash(confirm.token());
+
+    in_txn(|txn| {
+        let user_id = db::user_id_by_token_hash(txn, &token_hash)?
+            .pop()
+            .ok_or_else(|| MoziasApiErrKind::Unauthorized)?;
+
+        db::use_password_resets(txn, &user_id)?;
+        user::update_password(txn, &user_id, &new_hash, &user_id)?;
+        revoke_refresh_tokens(txn, &user_id)
+    })
+}
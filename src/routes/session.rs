@@ -0,0 +1,102 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Session Routes
+//!
+//! ```
+//! ```
+use crate::db::session as db;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::auth::RequireScope;
+use crate::guards::client::ClientInfo;
+use crate::guards::cookie::{build_cookie, SessionUser, CSRF_COOKIE, SESSION_COOKIE};
+use crate::lockout::Lockout;
+use crate::model::auth::SECONDS_PER_HOUR;
+use crate::model::scope::{SessionsRead, SessionsWrite};
+use crate::model::session::{Session, SessionCookie, SessionLogin, SessionLoginResponse};
+use crate::routes::auth::{check_password, device_session, revoke_session};
+use crate::routes::tfa;
+use crate::secret;
+use chrono::Utc;
+use mysql::Pool;
+use rocket::http::{Cookie, Cookies};
+use rocket::{delete, get, post, State};
+use rocket_contrib::json::Json;
+
+const SESSION_COOKIE_SECONDS: i64 = SECONDS_PER_HOUR * 12;
+
+#[post("/auth/session", data = "<login>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn login(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    client: ClientInfo,
+    mut cookies: Cookies<'_>,
+    login: Json<SessionLogin>,
+) -> MoziasApiResult<Json<SessionLoginResponse>> {
+    let username = login.username();
+    let (id, tfa_enabled) = check_password(&*pool, &*lockout, &client, username, login.password())?;
+
+    if tfa_enabled && !tfa::is_valid_code(&*pool, &*lockout, &id, login.code().as_ref())? {
+        return Err(MoziasApiErrKind::Unauthorized.into());
+    }
+
+    let csrf = secret::generate()?;
+    let mut session_cookie = SessionCookie::default();
+    let _ = session_cookie.set_sid(device_session(&id, &client)?);
+    let _ = session_cookie.set_aid(id);
+    let _ = session_cookie.set_sub(username.clone());
+    let _ = session_cookie.set_csrf(csrf.clone());
+    let _ = session_cookie.set_exp(Utc::now().timestamp() + SESSION_COOKIE_SECONDS);
+    let value =
+        serde_json::to_string(&session_cookie).map_err(|_| "unable to serialize session")?;
+
+    cookies.add_private(build_cookie(SESSION_COOKIE, value, true));
+    cookies.add(build_cookie(CSRF_COOKIE, csrf.clone(), false));
+
+    let mut login_response = SessionLoginResponse::default();
+    let _ = login_response.set_csrf_token(csrf);
+    Ok(Json(login_response))
+}
+
+#[delete("/auth/session")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn logout(user: SessionUser, mut cookies: Cookies<'_>) -> MoziasApiResult<()> {
+    cookies.remove_private(Cookie::named(SESSION_COOKIE));
+    cookies.remove(Cookie::named(CSRF_COOKIE));
+
+    let user = user.user();
+    revoke_session(user.aid(), user.sid()).map(|_| ())
+}
+
+#[get("/auth/sessions")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn sessions(
+    pool: State<'_, Pool>,
+    user: RequireScope<SessionsRead>,
+) -> MoziasApiResult<Json<Vec<Session>>> {
+    let user = user.user();
+    let mut sessions = db::sessions_by_user_id(&*pool, user.aid())?;
+
+    for session in &mut sessions {
+        let current = session.id() == user.sid();
+        let _ = session.set_current(current);
+    }
+
+    Ok(Json(sessions))
+}
+
+#[delete("/auth/sessions/<id>")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn delete(user: RequireScope<SessionsWrite>, id: String) -> MoziasApiResult<()> {
+    if revoke_session(user.user().aid(), &id)? {
+        Ok(())
+    } else {
+        Err(MoziasApiErrKind::NotFound.into())
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Two-Factor Authentication Routes
+//!
+//! ```
+//! ```
+use crate::db::auth as db;
+use crate::denylist::SharedDenylist;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::auth::OwnAccount;
+use crate::guards::client::ClientInfo;
+use crate::lockout::Lockout;
+use crate::model::auth::{TfaCode, TfaEnrollment, TfaTokenRequest, TokenResponse, TokenType};
+use crate::routes::auth::refresh_token;
+use crate::{token, totp};
+use mysql::Pool;
+use rocket::{delete, post, State};
+use rocket_contrib::json::Json;
+
+#[post("/auth/tfa")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn enroll(
+    pool: State<'_, Pool>,
+    user: OwnAccount,
+) -> MoziasApiResult<Json<TfaEnrollment>> {
+    let user = user.user();
+    let tfa_vec = db::tfa_info_by_user_id(&*pool, user.aid())?;
+
+    if tfa_vec.len() == 1 {
+        // Re-enrolling would silently turn 2FA off, so make them disable it first
+        if tfa_vec[0].3 {
+            return Err(MoziasApiErrKind::Conflict.into());
+        }
+
+        let secret = totp::generate_secret()?;
+        db::update_tfa(&*pool, user.aid(), Some(&secret), false)?;
+
+        let mut enrollment = TfaEnrollment::default();
+        let _ = enrollment.set_otpauth_uri(totp::otpauth_uri(user.sub(), &secret));
+        let _ = enrollment.set_secret(secret);
+        Ok(Json(enrollment))
+    } else {
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
+
+#[post("/auth/tfa/verify", data = "<tfa_code>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn verify(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    user: OwnAccount,
+    tfa_code: Json<TfaCode>,
+) -> MoziasApiResult<()> {
+    let user = user.user();
+    let tfa_vec = db::tfa_info_by_user_id(&*pool, user.aid())?;
+
+    if tfa_vec.len() == 1 {
+        match &tfa_vec[0].2 {
+            Some(secret) if check_code(&*pool, &*lockout, user.aid(), secret, tfa_code.code())? => {
+                db::update_tfa(&*pool, user.aid(), Some(secret.as_str()), true)
+            }
+            _ => Err(MoziasApiErrKind::Unauthorized.into()),
+        }
+    } else {
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
+
+#[delete("/auth/tfa", data = "<tfa_code>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn disable(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    user: OwnAccount,
+    tfa_code: Json<TfaCode>,
+) -> MoziasApiResult<()> {
+    let user = user.user();
+    let tfa_vec = db::tfa_info_by_user_id(&*pool, user.aid())?;
+
+    if tfa_vec.len() == 1 && tfa_vec[0].3 {
+        match &tfa_vec[0].2 {
+            Some(secret) if check_code(&*pool, &*lockout, user.aid(), secret, tfa_code.code())? => {
+                db::update_tfa(&*pool, user.aid(), None, false)
+            }
+            _ => Err(MoziasApiErrKind::Unauthorized.into()),
+        }
+    } else {
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
+
+#[post("/auth/tfa/token", data = "<tfa_request>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn exchange(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    denylist: State<'_, SharedDenylist>,
+    client: ClientInfo,
+    tfa_request: Json<TfaTokenRequest>,
+) -> MoziasApiResult<Json<TokenResponse>> {
+    let claims =
+        token::decode(tfa_request.tfa_token()).map_err(|_| MoziasApiErrKind::Unauthorized)?;
+
+    if *claims.typ() != TokenType::Access || !*claims.tfa() || denylist.is_denied(claims.jti()) {
+        return Err(MoziasApiErrKind::Unauthorized.into());
+    }
+
+    let id = claims.aid();
+    let tfa_vec = db::tfa_info_by_user_id(&*pool, id)?;
+
+    // Disabling an account takes effect even between the password and the code
+    if tfa_vec.len() == 1 && !tfa_vec[0].1 && tfa_vec[0].3 {
+        let username = &tfa_vec[0].0;
+
+        match &tfa_vec[0].2 {
+            Some(secret) if check_code(&*pool, &*lockout, id, secret, tfa_request.code())? => {
+                let (_, refresh_tok) = refresh_token(&*pool, id, username, &client, None)?;
+                let mut token_response = TokenResponse::default();
+                let _ = token_response.set_refresh_token(Some(refresh_tok));
+                Ok(Json(token_response))
+            }
+            _ => Err(MoziasApiErrKind::Unauthorized.into()),
+        }
+    } else {
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
+
+/// Check a TOTP code given alongside a password by a user with 2FA enabled.
+crate fn is_valid_code(
+    pool: &Pool,
+    lockout: &Lockout,
+    user_id: &str,
+    code: Option<&String>,
+) -> MoziasApiResult<bool> {
+    let tfa_vec = db::tfa_info_by_user_id(pool, user_id)?;
+
+    match (tfa_vec.first(), code) {
+        (Some((_, _, Some(secret), _)), Some(code)) => {
+            check_code(pool, lockout, user_id, secret, code)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Check a TOTP code, counting failures towards the user's lockout and
+/// refusing a time step that has already been used.
+fn check_code(
+    pool: &Pool,
+    lockout: &Lockout,
+    user_id: &str,
+    secret: &str,
+    code: &str,
+) -> MoziasApiResult<bool> {
+    Lockout::check_tfa(pool, user_id)?;
+
+    match totp::verify(secret, code) {
+        Some(step) if db::use_tfa_step(pool, user_id, step)? => {
+            Lockout::record_tfa_success(pool, user_id)?;
+            Ok(true)
+        }
+        _ => {
+            lockout.record_tfa_failure(user_id)?;
+            Ok(false)
+        }
+    }
+}
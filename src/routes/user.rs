@@ -0,0 +1,130 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! User Routes
+//!
+//! ```
+//! ```
+use crate::db::in_txn;
+use crate::db::user as db;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::guards::auth::RequireScope;
+use crate::guards::client::ClientInfo;
+use crate::guards::cookie::OwnSession;
+use crate::lockout::Lockout;
+use crate::model::auth::{User, UserProfile};
+use crate::model::oidc::UserInfo;
+use crate::model::scope::OpenId;
+use crate::model::user::{PasswordChange, Registration, UserResponse};
+use crate::password;
+use crate::routes::auth::revoke_refresh_tokens;
+use mysql::Pool;
+use rocket::response::status::Created;
+use rocket::{get, post, put, State};
+use rocket_contrib::json::Json;
+use uuid::Uuid;
+
+#[post("/users", data = "<registration>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn register(
+    registration: Json<Registration>,
+) -> MoziasApiResult<Created<Json<UserResponse>>> {
+    let username = registration.username().trim();
+
+    if username.is_empty() || registration.password().is_empty() {
+        return Err(MoziasApiErrKind::BadRequest.into());
+    }
+
+    let id = Uuid::new_v4().to_hyphenated().to_string();
+    let mut user = User::default();
+    let _ = user.set_id(id.clone());
+    let _ = user.set_username(username.to_string());
+    let _ = user.set_password(password::hash(registration.password())?);
+    let _ = user.set_name(registration.name().clone());
+    // Self-registered users create themselves
+    let _ = user.set_created_by(id.clone());
+    let _ = user.set_last_modified_by(id.clone());
+
+    let mut profile = UserProfile::default();
+    let _ = profile.set_id(Uuid::new_v4().to_hyphenated().to_string());
+    let _ = profile.set_user_id(id.clone());
+    let _ = profile.set_created_by(id.clone());
+    let _ = profile.set_last_modified_by(id.clone());
+
+    in_txn(|txn| {
+        if db::username_exists(txn, username)? {
+            return Err(MoziasApiErrKind::Conflict.into());
+        }
+        db::insert_user(txn, &user)?;
+        db::insert_user_profile(txn, &profile)
+    })?;
+
+    let mut user_response = UserResponse::default();
+    let _ = user_response.set_id(id.clone());
+    let _ = user_response.set_username(user.username().clone());
+    let _ = user_response.set_name(user.name().clone());
+    Ok(Created(
+        format!("/api/v1/users/{}", id),
+        Some(Json(user_response)),
+    ))
+}
+
+#[put("/users/me/password", data = "<change>", format = "application/json")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn change_password(
+    pool: State<'_, Pool>,
+    lockout: State<'_, Lockout>,
+    client: ClientInfo,
+    user: OwnSession,
+    change: Json<PasswordChange>,
+) -> MoziasApiResult<()> {
+    let user = user.user();
+
+    if change.new_password().is_empty() {
+        return Err(MoziasApiErrKind::BadRequest.into());
+    }
+
+    // A stolen session mustn't be a way to guess the password without limit
+    Lockout::check(&*pool, user.sub(), client.ip_str())?;
+
+    let hash_vec = db::password_by_user_id(&*pool, user.aid())?;
+
+    if hash_vec.len() == 1 && password::verify(&hash_vec[0], change.current_password())? {
+        Lockout::record_success(&*pool, user.sub())?;
+        let new_hash = password::hash(change.new_password())?;
+
+        // Everything logged in with the old password has to log in again
+        in_txn(|txn| {
+            db::update_password(txn, user.aid(), &new_hash, user.aid())?;
+            revoke_refresh_tokens(txn, user.aid())
+        })
+    } else {
+        lockout.record_failure(user.sub(), client.ip_str())?;
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
+
+#[get("/userinfo")]
+#[allow(clippy::needless_pass_by_value)]
+crate fn userinfo(
+    pool: State<'_, Pool>,
+    user: RequireScope<OpenId>,
+) -> MoziasApiResult<Json<UserInfo>> {
+    let user = user.user();
+    let names_vec = db::names_by_user_id(&*pool, user.aid())?;
+
+    if let Some((username, name)) = names_vec.first() {
+        let mut user_info = UserInfo::default();
+        let _ = user_info.set_sub(user.aid().clone());
+        let _ = user_info.set_name(name.clone());
+        let _ = user_info.set_preferred_username(username.clone());
+        Ok(Json(user_info))
+    } else {
+        Err(MoziasApiErrKind::Unauthorized.into())
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Well-Known Routes (RFC 8615)
+//!
+//! ```
+//! ```
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::keys;
+use crate::model::jwks::Jwks;
+use crate::model::oauth::{
+    GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, PKCE_S256, RESPONSE_TYPE_CODE,
+};
+use crate::model::oidc::{OpenIdConfiguration, PUBLIC_URL};
+use crate::model::scope::SCOPES;
+use rocket::get;
+use rocket_contrib::json::Json;
+
+#[get("/.well-known/jwks.json")]
+crate fn jwks() -> MoziasApiResult<Json<Jwks>> {
+    Ok(Json(keys::key_set()?.jwks()))
+}
+
+/// Only served when there is an asymmetric key, since without one no ID tokens
+/// are issued.
+#[get("/.well-known/openid-configuration")]
+crate fn openid_configuration() -> MoziasApiResult<Json<OpenIdConfiguration>> {
+    let signing_key = keys::key_set()?
+        .signing_key()
+        .ok_or_else(|| MoziasApiErrKind::NotFound)?;
+    let api = format!("{}/api/v1", *PUBLIC_URL);
+    let mut configuration = OpenIdConfiguration::default();
+    let _ = configuration.set_issuer(PUBLIC_URL.clone());
+    let _ = configuration.set_authorization_endpoint(format!("{}/oauth/authorize", api));
+    let _ = configuration.set_token_endpoint(format!("{}/oauth/token", api));
+    let _ = configuration.set_userinfo_endpoint(format!("{}/userinfo", api));
+    let _ = configuration.set_introspection_endpoint(format!("{}/auth/introspect", api));
+    let _ = configuration.set_jwks_uri(format!("{}/.well-known/jwks.json", *PUBLIC_URL));
+    let _ = configuration.set_scopes_supported(strings(&SCOPES));
+    let _ = configuration.set_response_types_supported(strings(&[RESPONSE_TYPE_CODE]));
+    let _ = configuration.set_grant_types_supported(strings(&[
+        GRANT_AUTHORIZATION_CODE,
+        GRANT_CLIENT_CREDENTIALS,
+    ]));
+    let _ = configuration.set_subject_types_supported(strings(&["public"]));
+    let _ = configuration.set_id_token_signing_alg_values_supported(strings(&[signing_key.alg()]));
+    let _ = configuration
+        .set_token_endpoint_auth_methods_supported(strings(&["client_secret_basic", "none"]));
+    let _ = configuration.set_code_challenge_methods_supported(strings(&[PKCE_S256]));
+    let _ = configuration.set_claims_supported(strings(&[
+        "iss",
+        "sub",
+        "aud",
+        "exp",
+        "iat",
+        "auth_time",
+        "nonce",
+        "name",
+        "preferred_username",
+    ]));
+    Ok(Json(configuration))
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
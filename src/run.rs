@@ -10,20 +10,73 @@
 //!
 //! ```
 //! ```
+use crate::catchers;
 use crate::db;
+use crate::denylist::Denylist;
 use crate::error::MoziasApiResult;
+use crate::fairings::audit::Audit;
 use crate::fairings::telemetry::Telemetry;
-use crate::routes::{auth, system};
-use rocket::routes;
+use crate::keys;
+use crate::lockout::Lockout;
+use crate::notify::{FileNotifier, SharedNotifier};
+use crate::password;
+use crate::routes::{api_key, auth, oauth, reset, session, system, tfa, user, well_known};
+use rocket::{catchers, routes};
 use rocket_contrib::serve::StaticFiles;
+use std::sync::Arc;
 
 crate fn run() -> MoziasApiResult<()> {
     let pool = db::get_pool()?;
+    password::init_dummy_hash()?;
+    keys::init_keys()?;
+    let notifier: SharedNotifier = Arc::new(FileNotifier::from_env());
+    let denylist = Denylist::start(&pool)?;
     Err(rocket::ignite()
         .manage(pool)
+        .manage(notifier)
+        .manage(Lockout::from_env())
+        .manage(denylist)
         .attach(Telemetry::default())
+        .attach(Audit::default())
+        .register(catchers![catchers::forbidden, catchers::unauthorized])
         .mount("/", StaticFiles::from("static"))
-        .mount("/api/v1", routes![system::healthcheck, auth::auth])
+        .mount(
+            "/",
+            routes![well_known::jwks, well_known::openid_configuration],
+        )
+        .mount(
+            "/api/v1",
+            routes![
+                system::healthcheck,
+                auth::auth,
+                auth::refresh,
+                auth::logout,
+                auth::revoke,
+                auth::deny,
+                auth::impersonate,
+                auth::unlock,
+                auth::introspect,
+                oauth::authorize,
+                oauth::login,
+                oauth::grant,
+                session::sessions,
+                session::delete,
+                session::login,
+                session::logout,
+                tfa::enroll,
+                tfa::verify,
+                tfa::disable,
+                tfa::exchange,
+                user::register,
+                user::change_password,
+                user::userinfo,
+                api_key::create,
+                api_key::list,
+                api_key::revoke,
+                reset::request,
+                reset::confirm
+            ],
+        )
         .launch()
         .into())
 }
@@ -0,0 +1,63 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Opaque Secrets
+//!
+//! ```
+//! ```
+use crate::error::MoziasApiResult;
+use ring::digest::{self, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+
+const SECRET_LEN: usize = 32;
+
+/// Generate a random, hex encoded secret suitable for handing to a user once.
+crate fn generate() -> MoziasApiResult<String> {
+    let mut secret = [0_u8; SECRET_LEN];
+    SystemRandom::new()
+        .fill(&mut secret)
+        .map_err(|_| "unable to generate secret")?;
+    Ok(to_hex(&secret))
+}
+
+/// The hex encoded SHA-256 of the given secret, for storage and lookup.
+crate fn hash(secret: &str) -> String {
+    to_hex(digest::digest(&SHA256, secret.as_bytes()).as_ref())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate, hash, SECRET_LEN};
+
+    #[test]
+    fn hash_is_hex_sha256() {
+        // FIPS 180-2 appendix B.1
+        assert_eq!(
+            hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn generate_is_hex() {
+        let secret = generate().expect("generated secret");
+        assert_eq!(secret.len(), SECRET_LEN * 2);
+        assert!(secret
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert_ne!(secret, generate().expect("generated secret"));
+    }
+}
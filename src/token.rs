@@ -0,0 +1,150 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! JSON Web Tokens
+//!
+//! Tokens are signed RS256 or ES256 with the active key from `keys` when one is
+//! configured, and HS512 with `JWT_SECRET` otherwise.  Once keys are configured
+//! HS512 tokens are only accepted until `MOZIAS_JWT_HS512_UNTIL`, a unix
+//! timestamp, so tokens issued before the switch can be honoured until they
+//! expire without `JWT_SECRET` staying good forever.
+//!
+//! ```
+//! ```
+use crate::config::env_or;
+use crate::error::{MoziasApiErrKind, MoziasApiResult};
+use crate::keys::{self, Key, KeySet};
+use crate::model::auth::{Claims, ISSUER};
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, Header, Validation};
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+
+const HS512: &str = "HS512";
+
+lazy_static! {
+    static ref HS512_UNTIL: i64 = env_or("MOZIAS_JWT_HS512_UNTIL", 0);
+}
+
+#[derive(Serialize)]
+struct JwsHeader<'a> {
+    alg: &'a str,
+    typ: &'a str,
+    kid: &'a str,
+}
+
+#[derive(Deserialize)]
+struct UnverifiedHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+/// Sign the given claims with the active key, or `JWT_SECRET` if there is none.
+crate fn encode<T>(claims: &T) -> MoziasApiResult<String>
+where
+    T: serde::Serialize,
+{
+    if let Some(key) = keys::key_set()?.signing_key() {
+        encode_with_key(key, claims)
+    } else {
+        let mut header = Header::default();
+        header.alg = Algorithm::HS512;
+        let secret = env::var("JWT_SECRET")?;
+        Ok(jsonwebtoken::encode(&header, claims, secret.as_bytes())?)
+    }
+}
+
+crate fn encode_with_key<T>(key: &Key, claims: &T) -> MoziasApiResult<String>
+where
+    T: serde::Serialize,
+{
+    let header = JwsHeader {
+        alg: key.alg(),
+        typ: "JWT",
+        kid: key.kid(),
+    };
+    let header_json = serde_json::to_vec(&header).map_err(|_| "unable to serialize header")?;
+    let claims_json = serde_json::to_vec(claims).map_err(|_| "unable to serialize claims")?;
+    let signing_input = format!(
+        "{}.{}",
+        keys::base64url(&header_json),
+        keys::base64url(&claims_json)
+    );
+    let signature = key.sign(signing_input.as_bytes())?;
+
+    Ok(format!("{}.{}", signing_input, keys::base64url(&signature)))
+}
+
+/// Verify the signature, `iss`, `nbf`, and `exp` of the given token and return its claims.
+crate fn decode(token: &str) -> MoziasApiResult<Claims> {
+    let key_set = keys::key_set()?;
+    let accept_hs512 = key_set.signing_key().is_none() || *HS512_UNTIL > Utc::now().timestamp();
+    decode_with(key_set, token, accept_hs512)
+}
+
+/// `decode` against the given keys, accepting HS512 only if `accept_hs512` is set.
+crate fn decode_with(
+    key_set: &KeySet,
+    token: &str,
+    accept_hs512: bool,
+) -> MoziasApiResult<Claims> {
+    let header: UnverifiedHeader = decode_segment(token.split('.').next().unwrap_or(""))?;
+
+    if header.alg == HS512 {
+        if !accept_hs512 {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let mut validation = Validation::new(Algorithm::HS512);
+        validation.validate_nbf = true;
+        validation.iss = Some(ISSUER.to_string());
+        let secret = env::var("JWT_SECRET")?;
+        Ok(jsonwebtoken::decode::<Claims>(token, secret.as_bytes(), &validation)?.claims)
+    } else {
+        let kid = header.kid.ok_or(MoziasApiErrKind::Unauthorized)?;
+        let key = key_set.find(&kid).ok_or(MoziasApiErrKind::Unauthorized)?;
+
+        // Never let the token pick a different algorithm than the key was made for
+        if header.alg != key.alg() {
+            return Err(MoziasApiErrKind::Unauthorized.into());
+        }
+
+        let split = token.rfind('.').ok_or(MoziasApiErrKind::Unauthorized)?;
+        let (signing_input, signature) = (&token[..split], &token[split + 1..]);
+        key.verify(signing_input.as_bytes(), &decode_base64url(signature)?)?;
+
+        let claims: Claims = decode_segment(signing_input.splitn(2, '.').nth(1).unwrap_or(""))?;
+        validate(&claims)?;
+        Ok(claims)
+    }
+}
+
+fn validate(claims: &Claims) -> MoziasApiResult<()> {
+    let now = Utc::now().timestamp();
+
+    if *claims.exp() < now || *claims.nbf() > now || *claims.iss() != *ISSUER {
+        Err(MoziasApiErrKind::Unauthorized.into())
+    } else {
+        Ok(())
+    }
+}
+
+fn decode_segment<T>(segment: &str) -> MoziasApiResult<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    serde_json::from_slice(&decode_base64url(segment)?)
+        .map_err(|_| MoziasApiErrKind::Unauthorized.into())
+}
+
+fn decode_base64url(segment: &str) -> MoziasApiResult<Vec<u8>> {
+    base64::decode_config(segment, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| MoziasApiErrKind::Unauthorized.into())
+}
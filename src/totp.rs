@@ -0,0 +1,137 @@
+// Copyright © 2019 mozias-api developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Time-based One-Time Passwords (RFC 6238)
+//!
+//! ```
+//! ```
+use crate::error::MoziasApiResult;
+use crate::model::auth::URL_ENC_ISSUER;
+use base32::Alphabet;
+use chrono::Utc;
+use ring::constant_time::verify_slices_are_equal;
+use ring::digest::SHA1;
+use ring::hmac::{self, SigningKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use rocket::http::uri::Uri;
+
+const SECRET_LEN: usize = 20;
+const DIGITS: usize = 6;
+const MODULUS: u32 = 1_000_000;
+const PERIOD: i64 = 30;
+// Accept codes from one period either side of now to allow for clock drift
+const SKEW: i64 = 1;
+const BASE32: Alphabet = Alphabet::RFC4648 { padding: false };
+
+/// Generate a new base32 encoded TOTP secret
+crate fn generate_secret() -> MoziasApiResult<String> {
+    let mut secret = [0_u8; SECRET_LEN];
+    SystemRandom::new()
+        .fill(&mut secret)
+        .map_err(|_| "unable to generate tfa secret")?;
+    Ok(base32::encode(BASE32, &secret))
+}
+
+/// Build the `otpauth://` URI authenticator apps use to enroll the secret
+crate fn otpauth_uri(username: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = *URL_ENC_ISSUER,
+        username = Uri::percent_encode(username),
+        secret = secret,
+        digits = DIGITS,
+        period = PERIOD,
+    )
+}
+
+/// Check the given code against the current time step (+/- `SKEW`), returning
+/// the step it matched so the caller can refuse to accept it twice.
+crate fn verify(secret: &str, code: &str) -> Option<i64> {
+    verify_at(secret, code, Utc::now().timestamp())
+}
+
+fn verify_at(secret: &str, code: &str, now: i64) -> Option<i64> {
+    let key = base32::decode(BASE32, secret)?;
+    let signing_key = SigningKey::new(&SHA1, &key);
+    let step = now / PERIOD;
+
+    (step - SKEW..=step + SKEW).find(|counter| {
+        #[allow(clippy::cast_sign_loss)]
+        let expected = hotp(&signing_key, *counter as u64);
+        verify_slices_are_equal(expected.as_bytes(), code.trim().as_bytes()).is_ok()
+    })
+}
+
+fn hotp(signing_key: &SigningKey, counter: u64) -> String {
+    let signature = hmac::sign(signing_key, &counter.to_be_bytes());
+    let digest = signature.as_ref();
+    let offset = usize::from(digest[digest.len() - 1] & 0x0f);
+    let binary = (u32::from(digest[offset]) & 0x7f) << 24
+        | u32::from(digest[offset + 1]) << 16
+        | u32::from(digest[offset + 2]) << 8
+        | u32::from(digest[offset + 3]);
+    format!("{:0width$}", binary % MODULUS, width = DIGITS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hotp, verify_at, BASE32, PERIOD};
+    use ring::digest::SHA1;
+    use ring::hmac::SigningKey;
+
+    // The SHA-1 seed from RFC 6238 appendix B, "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    // RFC 6238 appendix B, truncated to six digits
+    const RFC_VECTORS: [(i64, &str); 6] = [
+        (59, "287082"),
+        (1_111_111_109, "081804"),
+        (1_111_111_111, "050471"),
+        (1_234_567_890, "005924"),
+        (2_000_000_000, "279037"),
+        (20_000_000_000, "353130"),
+    ];
+
+    #[test]
+    fn hotp_matches_rfc_6238() {
+        let key = base32::decode(BASE32, RFC_SECRET).expect("valid base32");
+        assert_eq!(key, b"12345678901234567890");
+        let signing_key = SigningKey::new(&SHA1, &key);
+
+        for (time, code) in &RFC_VECTORS {
+            #[allow(clippy::cast_sign_loss)]
+            let counter = (time / PERIOD) as u64;
+            assert_eq!(hotp(&signing_key, counter), *code);
+        }
+    }
+
+    #[test]
+    fn verify_returns_matched_step() {
+        for (time, code) in &RFC_VECTORS {
+            assert_eq!(verify_at(RFC_SECRET, code, *time), Some(time / PERIOD));
+        }
+    }
+
+    #[test]
+    fn verify_allows_skew() {
+        let (time, code) = RFC_VECTORS[3];
+        let step = time / PERIOD;
+        assert_eq!(verify_at(RFC_SECRET, code, time + PERIOD), Some(step));
+        assert_eq!(verify_at(RFC_SECRET, code, time - PERIOD), Some(step));
+        assert_eq!(verify_at(RFC_SECRET, code, time + 2 * PERIOD), None);
+        assert_eq!(verify_at(RFC_SECRET, code, time - 2 * PERIOD), None);
+    }
+
+    #[test]
+    fn verify_rejects_bad_input() {
+        let (time, _) = RFC_VECTORS[3];
+        assert_eq!(verify_at(RFC_SECRET, "000000", time), None);
+        assert_eq!(verify_at(RFC_SECRET, "", time), None);
+        assert_eq!(verify_at("not base32!", "005924", time), None);
+    }
+}